@@ -0,0 +1,128 @@
+use std::collections::HashMap;
+use std::f32::consts::PI;
+use std::time::Duration;
+
+use specs::prelude::*;
+
+use sdl2::pixels::Color;
+
+use crate::color;
+use crate::console::{Animation, Console};
+use crate::cp437::Cp437;
+use crate::glyph::Glyph;
+use crate::keyframes::AnimationLibrary;
+use crate::layers::Layers;
+
+// Seconds a shifted glyph spends in each position
+const SHIFT_PERIOD: f32 = 0.5;
+
+/// Time since the last frame, and in total, for animating.
+#[derive(Debug, Default)]
+pub struct FrameTime {
+    pub delta: f32,
+    pub elapsed: f32,
+}
+
+impl FrameTime {
+    pub fn advance(&mut self, delta: Duration) {
+        self.delta = delta.as_secs_f32();
+        self.elapsed += self.delta;
+    }
+}
+
+/// Draws the animations of the composited layers onto the console. The layers keep
+/// their tiles as they are, animations only change what is drawn.
+pub struct AnimationSys;
+
+impl<'a> System<'a> for AnimationSys {
+    type SystemData = (
+        Read<'a, FrameTime>,
+        Read<'a, AnimationLibrary>,
+        Read<'a, Layers>,
+        Write<'a, Console>,
+    );
+
+    fn run(&mut self, (time, library, layers, mut console): Self::SystemData) {
+        let base = layers.flattened();
+        if base.len() != console.tiles().len() {
+            return;
+        }
+        let (width, height) = (console.width() as usize, console.height() as usize);
+
+        // Start every cell an animation could touch from its unanimated look, so
+        // cells a shifted glyph has moved off are put back
+        let mut cells: HashMap<usize, (Glyph, Color, Color)> = HashMap::new();
+        for (index, _) in layers.animated() {
+            cells.insert(*index, base[*index]);
+            if index % width + 1 < width {
+                cells.insert(index + 1, base[index + 1]);
+            }
+            if index / width + 1 < height {
+                cells.insert(index + width, base[index + width]);
+            }
+        }
+
+        let mut shifted = Vec::new();
+        for (index, animations) in layers.animated() {
+            let (mut glyph, mut fg, mut bg) = base[*index];
+            let mut shift = None;
+            for animation in animations {
+                match animation {
+                    Animation::Blink(period) => {
+                        let period = *period;
+                        if period > 0.0 && time.elapsed % period >= period / 2.0 {
+                            glyph = Cp437::Space.into();
+                        }
+                    }
+                    Animation::ColorShift(period, from, to) => {
+                        let (period, from, to) = (*period, *from, *to);
+                        let t = if period > 0.0 {
+                            0.5 - 0.5 * (2.0 * PI * time.elapsed / period).cos()
+                        } else {
+                            0.0
+                        };
+                        fg = color::lerp(from, to, t);
+                    }
+                    Animation::HorizontalShift if index % width + 1 < width => {
+                        shift = Some(index + 1);
+                    }
+                    Animation::VerticalShift if index / width + 1 < height => {
+                        shift = Some(index + width);
+                    }
+                    Animation::HorizontalShift | Animation::VerticalShift => {}
+                    Animation::Keyframed { name, start } => {
+                        // Unknown names are left unanimated rather than failing the frame
+                        if let Some(keyframed) = library.get(name) {
+                            let cell = ((index % width) as u32, (index / width) as u32);
+                            let t = time.elapsed - start;
+                            (glyph, fg, bg) = keyframed.sample(t, cell, (glyph, fg, bg));
+                        }
+                    }
+                }
+            }
+            let moved = time.elapsed % (2.0 * SHIFT_PERIOD) >= SHIFT_PERIOD;
+            match shift {
+                Some(target) if moved => {
+                    cells.insert(*index, (Cp437::Space.into(), fg, bg));
+                    shifted.push((target, glyph, fg));
+                }
+                _ => {
+                    cells.insert(*index, (glyph, fg, bg));
+                }
+            }
+        }
+        // Moved glyphs go over whatever background is where they land
+        for (target, glyph, fg) in shifted {
+            let bg = cells[&target].2;
+            cells.insert(target, (glyph, fg, bg));
+        }
+
+        for (index, (glyph, fg, bg)) in cells {
+            let tile = &console.tiles()[index];
+            if tile.code_point != glyph || tile.foreground != fg || tile.background != bg {
+                let (x, y) = ((index % width) as i32, (index / width) as i32);
+                console.put(x, y, glyph, fg, bg);
+            }
+        }
+    }
+}
@@ -0,0 +1,394 @@
+use std::convert::TryInto;
+use std::fs;
+use std::path::Path;
+
+use sdl2::pixels::Color;
+
+use crate::console::Console;
+use crate::cp437::Cp437;
+
+// Text-mode colours in attribute byte order, the ANSI colour numbers differ
+const VGA: [(u8, u8, u8); 16] = [
+    (0, 0, 0),
+    (0, 0, 170),
+    (0, 170, 0),
+    (0, 170, 170),
+    (170, 0, 0),
+    (170, 0, 170),
+    (170, 85, 0),
+    (170, 170, 170),
+    (85, 85, 85),
+    (85, 85, 255),
+    (85, 255, 85),
+    (85, 255, 255),
+    (255, 85, 85),
+    (255, 85, 255),
+    (255, 255, 85),
+    (255, 255, 255),
+];
+// VGA index of each ANSI colour number
+const ANSI_TO_VGA: [usize; 8] = [0, 4, 2, 6, 1, 5, 3, 7];
+
+const DEFAULT_WIDTH: u32 = 80;
+const DEFAULT_BIN_WIDTH: u32 = 160;
+// Bounds the console ANSI art can ask for, cursor movement past it stops at the last row
+const MAX_CELLS: u32 = 1 << 20;
+const SAUCE_SIZE: usize = 128;
+const COMMENT_SIZE: usize = 64;
+// Marks the end of the art, anything after it is metadata
+const EOF: u8 = 0x1a;
+
+fn vga(index: usize) -> Color {
+    let (r, g, b) = VGA[index];
+    Color::RGB(r, g, b)
+}
+
+/// Metadata appended to many art files, see <http://www.acid.org/info/sauce/sauce.htm>.
+#[derive(Debug, Clone)]
+pub struct Sauce {
+    pub data_type: u8,
+    pub file_type: u8,
+    pub info: [u16; 4],
+    /// Blinking backgrounds are bright colours instead.
+    pub ice_colors: bool,
+}
+
+impl Sauce {
+    /// Splits `data` into the art and its SAUCE record, if it has one. The art keeps
+    /// the end of file byte usually written before the record.
+    pub fn split(data: &[u8]) -> (&[u8], Option<Sauce>) {
+        if data.len() < SAUCE_SIZE || !data[data.len() - SAUCE_SIZE..].starts_with(b"SAUCE00") {
+            return (data, None);
+        }
+        let record = &data[data.len() - SAUCE_SIZE..];
+        let word = |at: usize| u16::from_le_bytes(record[at..at + 2].try_into().unwrap());
+        let sauce = Sauce {
+            data_type: record[94],
+            file_type: record[95],
+            info: [word(96), word(98), word(100), word(102)],
+            ice_colors: record[105] & 1 != 0,
+        };
+
+        let mut end = data.len() - SAUCE_SIZE;
+        let comments = record[104] as usize;
+        let block = 5 + comments * COMMENT_SIZE;
+        if comments > 0 && end >= block && data[end - block..].starts_with(b"COMNT") {
+            end -= block;
+        }
+        (&data[..end], Some(sauce))
+    }
+
+    /// The width in characters, if the record gives one.
+    pub fn width(&self) -> Option<u32> {
+        match (self.data_type, self.file_type) {
+            // Binary text stores half the width in the file type
+            (5, file_type) if file_type > 0 => Some(file_type as u32 * 2),
+            (1, _) | (6, _) if self.info[0] > 0 => Some(self.info[0] as u32),
+            _ => None,
+        }
+    }
+
+    /// The height in lines, if the record gives one.
+    pub fn height(&self) -> Option<u32> {
+        match self.data_type {
+            1 | 6 if self.info[1] > 0 => Some(self.info[1] as u32),
+            _ => None,
+        }
+    }
+}
+
+/// Loads a raw text-mode dump if the file ends in `.bin`, and ANSI art otherwise.
+#[allow(dead_code)]
+pub fn load(path: &Path) -> Result<Console, String> {
+    let data = fs::read(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    let is_bin = path
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("bin"));
+    if is_bin {
+        parse_bin(&data).map_err(|e| format!("{}: {}", path.display(), e))
+    } else {
+        Ok(parse_ans(&data))
+    }
+}
+
+/// Parses a text-mode dump of character and attribute byte pairs, as wide as its
+/// SAUCE record says or 160 characters.
+pub fn parse_bin(data: &[u8]) -> Result<Console, String> {
+    // Attribute bytes can be the end of file byte, so only the SAUCE record is dropped.
+    // An end of file byte written before it is left over as half a cell.
+    let (data, sauce) = Sauce::split(data);
+    let width = sauce
+        .as_ref()
+        .and_then(Sauce::width)
+        .unwrap_or(DEFAULT_BIN_WIDTH);
+    let ice_colors = sauce.as_ref().is_some_and(|sauce| sauce.ice_colors);
+    let cells = data.len() / 2;
+    if cells == 0 {
+        return Err("no character data".to_string());
+    }
+    let height = (cells as u32).div_ceil(width);
+
+    let mut console = Console::new(width, height);
+    console.clear(Cp437::Space.into(), vga(7), vga(0));
+    for (i, pair) in data.chunks_exact(2).enumerate() {
+        let (x, y) = (i as u32 % width, i as u32 / width);
+        let attr = pair[1] as usize;
+        let bg = if ice_colors {
+            attr >> 4
+        } else {
+            (attr >> 4) & 7
+        };
+        console.put(
+            x as i32,
+            y as i32,
+            Cp437::from(pair[0]).into(),
+            vga(attr & 15),
+            vga(bg),
+        );
+    }
+    Ok(console)
+}
+
+#[derive(Debug, Copy, Clone)]
+struct Pen {
+    fg: usize,
+    bg: usize,
+    bold: bool,
+    blink: bool,
+    inverse: bool,
+    // Colours from 256 colour and truecolor sequences override the indices
+    fg_rgb: Option<Color>,
+    bg_rgb: Option<Color>,
+}
+
+impl Default for Pen {
+    fn default() -> Self {
+        Self {
+            fg: 7,
+            bg: 0,
+            bold: false,
+            blink: false,
+            inverse: false,
+            fg_rgb: None,
+            bg_rgb: None,
+        }
+    }
+}
+
+impl Pen {
+    fn colors(&self, ice_colors: bool) -> (Color, Color) {
+        let bright = |index: usize, on: bool| if on && index < 8 { index + 8 } else { index };
+        let fg = self
+            .fg_rgb
+            .unwrap_or_else(|| vga(bright(self.fg, self.bold)));
+        let bg = self
+            .bg_rgb
+            .unwrap_or_else(|| vga(bright(self.bg, self.blink && ice_colors)));
+        if self.inverse {
+            (bg, fg)
+        } else {
+            (fg, bg)
+        }
+    }
+
+    fn apply_sgr(&mut self, params: &[u32]) {
+        let mut params = params.iter().copied();
+        while let Some(param) = params.next() {
+            match param {
+                0 => *self = Pen::default(),
+                1 => self.bold = true,
+                5 => self.blink = true,
+                7 => self.inverse = true,
+                22 => self.bold = false,
+                25 => self.blink = false,
+                27 => self.inverse = false,
+                30..=37 => {
+                    self.fg = ANSI_TO_VGA[(param - 30) as usize];
+                    self.fg_rgb = None;
+                }
+                39 => {
+                    self.fg = 7;
+                    self.fg_rgb = None;
+                }
+                40..=47 => {
+                    self.bg = ANSI_TO_VGA[(param - 40) as usize];
+                    self.bg_rgb = None;
+                }
+                49 => {
+                    self.bg = 0;
+                    self.bg_rgb = None;
+                }
+                90..=97 => {
+                    self.fg = ANSI_TO_VGA[(param - 90) as usize] + 8;
+                    self.fg_rgb = None;
+                }
+                100..=107 => {
+                    self.bg = ANSI_TO_VGA[(param - 100) as usize] + 8;
+                    self.bg_rgb = None;
+                }
+                38 | 48 => {
+                    let color = match params.next() {
+                        Some(5) => params.next().map(xterm_color),
+                        Some(2) => match (params.next(), params.next(), params.next()) {
+                            (Some(r), Some(g), Some(b)) => {
+                                Some(Color::RGB(r as u8, g as u8, b as u8))
+                            }
+                            _ => None,
+                        },
+                        _ => None,
+                    };
+                    if param == 38 {
+                        self.fg_rgb = color;
+                    } else {
+                        self.bg_rgb = color;
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+fn xterm_color(index: u32) -> Color {
+    match index {
+        0..=7 => vga(ANSI_TO_VGA[index as usize]),
+        8..=15 => vga(ANSI_TO_VGA[index as usize - 8] + 8),
+        16..=231 => {
+            let level = |n: u32| if n == 0 { 0 } else { (55 + n * 40) as u8 };
+            let n = index - 16;
+            Color::RGB(level(n / 36), level(n / 6 % 6), level(n % 6))
+        }
+        _ => {
+            let grey = (8 + (index.min(255) - 232) * 10) as u8;
+            Color::RGB(grey, grey, grey)
+        }
+    }
+}
+
+/// Parses ANSI art: CP437 text with SGR colours and cursor movement, and an optional
+/// SAUCE record giving its size. Lines wrap at 80 characters unless the record says
+/// otherwise, and the console is as tall as the drawing.
+pub fn parse_ans(data: &[u8]) -> Console {
+    let (data, sauce) = Sauce::split(data);
+    let width = sauce
+        .as_ref()
+        .and_then(Sauce::width)
+        .unwrap_or(DEFAULT_WIDTH);
+    let ice_colors = sauce.as_ref().is_some_and(|sauce| sauce.ice_colors);
+    let last_row = (MAX_CELLS / width).max(1) - 1;
+
+    let mut rows: Vec<Vec<Option<(Cp437, Color, Color)>>> = Vec::new();
+    let (mut x, mut y) = (0u32, 0u32);
+    let mut saved = (0, 0);
+    let mut pen = Pen::default();
+
+    let mut i = 0;
+    while i < data.len() {
+        match data[i] {
+            0x1b if data.get(i + 1) == Some(&b'[') => {
+                // Parameters run up to the first byte in the final byte range
+                let start = i + 2;
+                let end = match data[start..].iter().position(|b| (0x40..=0x7e).contains(b)) {
+                    Some(len) => start + len,
+                    None => break,
+                };
+                let params: Vec<u32> = String::from_utf8_lossy(&data[start..end])
+                    .trim_start_matches('?')
+                    .split(';')
+                    .map(|p| p.parse().unwrap_or(0))
+                    .collect();
+                let n = params.first().copied().filter(|&n| n > 0).unwrap_or(1);
+                match data[end] {
+                    b'm' => pen.apply_sgr(&params),
+                    b'A' => y = y.saturating_sub(n),
+                    b'B' => y = y.saturating_add(n).min(last_row),
+                    b'C' => x = x.saturating_add(n).min(width - 1),
+                    b'D' => x = x.saturating_sub(n),
+                    b'H' | b'f' => {
+                        y = (n - 1).min(last_row);
+                        x = (params.get(1).copied().filter(|&n| n > 0).unwrap_or(1) - 1)
+                            .min(width - 1);
+                    }
+                    b'J' if params.first() == Some(&2) => {
+                        rows.clear();
+                        x = 0;
+                        y = 0;
+                    }
+                    b'K' => {
+                        if let Some(row) = rows.get_mut(y as usize) {
+                            row.truncate(x as usize);
+                        }
+                    }
+                    b's' => saved = (x, y),
+                    b'u' => {
+                        x = saved.0;
+                        y = saved.1;
+                    }
+                    _ => {}
+                }
+                i = end + 1;
+                continue;
+            }
+            EOF => break,
+            b'\r' => x = 0,
+            b'\n' => {
+                x = 0;
+                y = (y + 1).min(last_row);
+            }
+            byte => {
+                if x >= width {
+                    x = 0;
+                    y = (y + 1).min(last_row);
+                }
+                if rows.len() <= y as usize {
+                    rows.resize(y as usize + 1, Vec::new());
+                }
+                let row = &mut rows[y as usize];
+                if row.len() <= x as usize {
+                    row.resize(x as usize + 1, None);
+                }
+                let (fg, bg) = pen.colors(ice_colors);
+                row[x as usize] = Some((Cp437::from(byte), fg, bg));
+                x += 1;
+            }
+        }
+        i += 1;
+    }
+
+    let height = sauce
+        .as_ref()
+        .and_then(Sauce::height)
+        .unwrap_or(0)
+        .min(last_row + 1)
+        .max(rows.len() as u32)
+        .max(1);
+    let mut console = Console::new(width, height);
+    console.clear(Cp437::Space.into(), vga(7), vga(0));
+    for (y, row) in rows.iter().enumerate() {
+        for (x, cell) in row.iter().enumerate() {
+            if let Some((cp, fg, bg)) = cell {
+                console.put(x as i32, y as i32, (*cp).into(), *fg, *bg);
+            }
+        }
+    }
+    console
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bounds_cursor_movement() {
+        let console = parse_ans(b"a\x1b[4294967295C\x1b[4294967295Bb");
+        assert_eq!(console.width(), DEFAULT_WIDTH);
+        assert_eq!(console.height(), MAX_CELLS / DEFAULT_WIDTH);
+        let tile = console
+            .tile(DEFAULT_WIDTH - 1, console.height() - 1)
+            .unwrap();
+        assert_eq!(tile.code_point, Cp437::LatinSmallLetterB.into());
+
+        let console = parse_ans(b"\x1b[99999999Hc");
+        assert_eq!(console.height(), MAX_CELLS / DEFAULT_WIDTH);
+    }
+}
@@ -0,0 +1,60 @@
+use std::collections::HashSet;
+
+use sdl2::keyboard::Keycode;
+use sdl2::mouse::MouseButton;
+
+use crate::config::Config;
+use crate::console::Console;
+use crate::glyph::Glyph;
+use crate::headless::RgbaImage;
+
+/// Input gathered by a backend since the last poll.
+#[derive(Debug, Default)]
+pub struct Input {
+    /// The window was closed or the backend has nothing left to show.
+    pub quit: bool,
+    pub keycodes: HashSet<Keycode>,
+    pub mouse: Mouse,
+}
+
+/// The mouse in console cells, with anything outside the frame ignored.
+#[allow(dead_code)]
+#[derive(Debug, Default, Clone)]
+pub struct Mouse {
+    /// The cell under the pointer.
+    pub cell: Option<(u32, u32)>,
+    /// Whether the pointer moved to another cell since the last poll.
+    pub moved: bool,
+    pub held: HashSet<MouseButton>,
+    /// Buttons pressed since the last poll and the cells they were pressed on.
+    pub clicks: Vec<(MouseButton, (u32, u32))>,
+    /// Scrolling since the last poll, positive `y` is away from the user.
+    pub wheel: (i32, i32),
+}
+
+/// Somewhere to show consoles and read input from: an SDL window, the terminal or an
+/// offscreen image. The game loop only goes through this.
+pub trait Backend {
+    fn poll_input(&mut self) -> Result<Input, String>;
+
+    /// Draws the dirty tiles of `console`, or all of them after a resize, and shows
+    /// the frame.
+    fn present(&mut self, console: &Console) -> Result<(), String>;
+
+    /// Prepares for consoles of `size` cells, called before the first `present`.
+    fn resize(&mut self, size: (u32, u32)) -> Result<(), String>;
+
+    /// Applies display settings, ignored by backends they don't apply to.
+    fn configure(&mut self, config: &Config) -> Result<(), String>;
+
+    /// The console size in cells that fills the window or terminal, or `None` if the
+    /// output has no size of its own.
+    fn fitting_size(&self) -> Option<(u32, u32)>;
+
+    /// Looks up a glyph by the name given to it in the glyph source, see `GlyphMap`.
+    fn glyph(&self, name: &str) -> Option<Glyph>;
+
+    /// Reads back the last frame presented, or `None` if the backend doesn't draw
+    /// pixels.
+    fn screenshot(&mut self) -> Result<Option<RgbaImage>, String>;
+}
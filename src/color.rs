@@ -0,0 +1,25 @@
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use sdl2::pixels::Color;
+
+/// Linearly interpolates from `a` to `b`, with `t` clamped to `0.0..=1.0`.
+pub fn lerp(a: Color, b: Color, t: f32) -> Color {
+    let t = t.clamp(0.0, 1.0);
+    let mix = |a: u8, b: u8| (a as f32 + (b as f32 - a as f32) * t).round() as u8;
+    Color::RGBA(mix(a.r, b.r), mix(a.g, b.g), mix(a.b, b.b), mix(a.a, b.a))
+}
+
+/// Serialises a `Color` as an `(r, g, b, a)` tuple, for fields marked
+/// `#[serde(with = "color::rgba")]`.
+pub mod rgba {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(color: &Color, serializer: S) -> Result<S::Ok, S::Error> {
+        (color.r, color.g, color.b, color.a).serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Color, D::Error> {
+        let (r, g, b, a) = Deserialize::deserialize(deserializer)?;
+        Ok(Color::RGBA(r, g, b, a))
+    }
+}
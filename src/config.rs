@@ -0,0 +1,77 @@
+use std::fs::{self, File};
+use std::io;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::glyph::Glyph;
+
+/// How the frame is scaled up to the window.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Scaling {
+    /// Whole multiples of the frame size with nearest neighbour filtering, falling
+    /// back on `Fit` when the window is smaller than the frame.
+    Integer,
+    /// As large as fits while keeping the aspect ratio.
+    Fit,
+    /// Fills the window.
+    Stretch,
+}
+
+impl Scaling {
+    pub fn next(self) -> Self {
+        match self {
+            Scaling::Integer => Scaling::Fit,
+            Scaling::Fit => Scaling::Stretch,
+            Scaling::Stretch => Scaling::Integer,
+        }
+    }
+}
+
+/// What fills the window around the frame.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Letterbox {
+    Color(u8, u8, u8),
+    /// A glyph repeated on the frame's grid.
+    Pattern {
+        glyph: Glyph,
+        foreground: (u8, u8, u8),
+        background: (u8, u8, u8),
+    },
+}
+
+/// Display settings kept between runs.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    pub scaling: Scaling,
+    pub letterbox: Letterbox,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            scaling: Scaling::Integer,
+            letterbox: Letterbox::Color(0, 0, 0),
+        }
+    }
+}
+
+impl Config {
+    /// Loads the config at `path`, or the defaults if there isn't one yet.
+    pub fn load(path: &Path) -> Result<Self, String> {
+        match File::open(path) {
+            Ok(file) => {
+                ron::de::from_reader(file).map_err(|e| format!("{}: {}", path.display(), e))
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(format!("{}: {}", path.display(), e)),
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        let text =
+            ron::ser::to_string_pretty(self, Default::default()).map_err(|e| e.to_string())?;
+        fs::write(path, text).map_err(|e| format!("{}: {}", path.display(), e))
+    }
+}
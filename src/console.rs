@@ -0,0 +1,861 @@
+use std::convert::TryFrom;
+use std::fs;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use sdl2::pixels::Color;
+use sdl2::rect::Rect;
+
+use crate::color;
+use crate::cp437::Cp437;
+use crate::glyph::Glyph;
+use crate::markup::{self, MarkupError};
+use crate::palette::Palette;
+
+/// Changes how a tile is drawn over time without changing the tile, see
+/// `animation::AnimationSys`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Animation {
+    /// Hides the glyph for the second half of every period, in seconds.
+    Blink(f32),
+    /// Moves the glyph down a cell and back every half second.
+    VerticalShift,
+    /// Moves the glyph right a cell and back every half second.
+    HorizontalShift,
+    /// Eases the foreground from one colour to the other and back over a period, in
+    /// seconds.
+    ColorShift(
+        f32,
+        #[serde(with = "color::rgba")] Color,
+        #[serde(with = "color::rgba")] Color,
+    ),
+    /// Plays the named animation from `keyframes::AnimationLibrary`, starting at
+    /// `start` seconds of `animation::FrameTime::elapsed`.
+    Keyframed { name: String, start: f32 },
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum FrameStyle {
+    Single,
+    Double,
+    /// Double horizontal edges with single vertical edges.
+    DoubleHorizontal,
+    /// Single horizontal edges with double vertical edges.
+    DoubleVertical,
+}
+
+impl FrameStyle {
+    // Corners clockwise from the top left, then the horizontal and vertical edges
+    fn glyphs(self) -> [Cp437; 6] {
+        match self {
+            FrameStyle::Single => [
+                Cp437::BoxDrawingsLightDownAndRight,
+                Cp437::BoxDrawingsLightDownAndLeft,
+                Cp437::BoxDrawingsLightUpAndLeft,
+                Cp437::BoxDrawingsLightUpAndRight,
+                Cp437::BoxDrawingsLightHorizontal,
+                Cp437::BoxDrawingsLightVertical,
+            ],
+            FrameStyle::Double => [
+                Cp437::BoxDrawingsDoubleDownAndRight,
+                Cp437::BoxDrawingsDoubleDownAndLeft,
+                Cp437::BoxDrawingsDoubleUpAndLeft,
+                Cp437::BoxDrawingsDoubleUpAndRight,
+                Cp437::BoxDrawingsDoubleHorizontal,
+                Cp437::BoxDrawingsDoubleVertical,
+            ],
+            FrameStyle::DoubleHorizontal => [
+                Cp437::BoxDrawingsDownSingleAndRightDouble,
+                Cp437::BoxDrawingsDownSingleAndLeftDouble,
+                Cp437::BoxDrawingsUpSingleAndLeftDouble,
+                Cp437::BoxDrawingsUpSingleAndRightDouble,
+                Cp437::BoxDrawingsDoubleHorizontal,
+                Cp437::BoxDrawingsLightVertical,
+            ],
+            FrameStyle::DoubleVertical => [
+                Cp437::BoxDrawingsDownDoubleAndRightSingle,
+                Cp437::BoxDrawingsDownDoubleAndLeftSingle,
+                Cp437::BoxDrawingsUpDoubleAndLeftSingle,
+                Cp437::BoxDrawingsUpDoubleAndRightSingle,
+                Cp437::BoxDrawingsLightHorizontal,
+                Cp437::BoxDrawingsDoubleVertical,
+            ],
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone)]
+pub struct BlitOptions {
+    /// Source tiles with this background are not copied.
+    pub key_background: Option<Color>,
+    /// Source tiles with this glyph are not copied.
+    pub key_glyph: Option<Glyph>,
+    pub foreground_alpha: f32,
+    pub background_alpha: f32,
+}
+
+impl Default for BlitOptions {
+    fn default() -> Self {
+        Self {
+            key_background: None,
+            key_glyph: None,
+            foreground_alpha: 1.0,
+            background_alpha: 1.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Tile {
+    pub row: u32,
+    pub col: u32,
+    pub code_point: Glyph,
+    #[serde(with = "color::rgba")]
+    pub foreground: Color,
+    #[serde(with = "color::rgba")]
+    pub background: Color,
+    /// Palette index the foreground follows, see `Palette::color`.
+    #[serde(default)]
+    pub foreground_index: Option<u16>,
+    #[serde(default)]
+    pub background_index: Option<u16>,
+    #[serde(skip, default = "dirty_default")]
+    dirty: bool,
+    #[serde(default)]
+    pub animations: Vec<Animation>,
+}
+
+// Deserialised tiles have never been drawn
+fn dirty_default() -> bool {
+    true
+}
+
+impl Tile {
+    #[allow(dead_code)]
+    pub fn dirty(&self) -> bool {
+        self.dirty
+    }
+}
+
+impl Default for Tile {
+    fn default() -> Self {
+        Self {
+            row: 0,
+            col: 0,
+            code_point: Cp437::QuestionMark.into(),
+            foreground: Color::RGBA(255, 0, 0, 255),
+            background: Color::RGBA(0, 0, 255, 255),
+            foreground_index: None,
+            background_index: None,
+            dirty: true,
+            animations: vec![],
+        }
+    }
+}
+
+/// A grid of tiles that keeps track of which ones changed since the last
+/// `reset_tiles`, so drawing only has to visit those.
+///
+/// Serialises as runs of identical tiles, see `save_ron`.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(into = "ConsoleRepr", try_from = "ConsoleRepr")]
+pub struct Console {
+    width: u32,
+    height: u32,
+    tiles: Vec<Tile>,
+    dirty: Vec<usize>,
+}
+
+impl Console {
+    pub fn new(width: u32, height: u32) -> Self {
+        let mut tiles = Vec::new();
+        for col in 0..height {
+            for row in 0..width {
+                tiles.push(Tile {
+                    row,
+                    col,
+                    ..Default::default()
+                })
+            }
+        }
+        let dirty = (0..tiles.len()).collect();
+        Self {
+            width,
+            height,
+            tiles,
+            dirty,
+        }
+    }
+
+    pub fn is_dirty(&self) -> bool {
+        !self.dirty.is_empty()
+    }
+
+    pub fn dirty_tiles(&self) -> impl Iterator<Item = &Tile> {
+        self.dirty.iter().map(move |&i| &self.tiles[i])
+    }
+
+    pub fn tiles(&self) -> &[Tile] {
+        &self.tiles
+    }
+
+    /// Gives unrestricted access to the tiles, so all of them are marked dirty.
+    pub fn tiles_mut(&mut self) -> &mut [Tile] {
+        for index in 0..self.tiles.len() {
+            self.mark_dirty(index);
+        }
+        &mut self.tiles
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn reset_tiles(&mut self) {
+        for index in self.dirty.drain(..) {
+            self.tiles[index].dirty = false;
+        }
+    }
+
+    pub fn tile(&self, x: u32, y: u32) -> Option<&Tile> {
+        if x >= self.width || y >= self.height {
+            return None;
+        }
+        Some(&self.tiles[self.index(x, y)])
+    }
+
+    /// Returns the tile at `(x, y)`, marking it dirty.
+    pub fn tile_mut(&mut self, x: u32, y: u32) -> Option<&mut Tile> {
+        if x >= self.width || y >= self.height {
+            return None;
+        }
+        let index = self.index(x, y);
+        self.mark_dirty(index);
+        Some(&mut self.tiles[index])
+    }
+
+    fn mark_dirty(&mut self, index: usize) {
+        if !self.tiles[index].dirty {
+            self.tiles[index].dirty = true;
+            self.dirty.push(index);
+        }
+    }
+
+    fn index(&self, x: u32, y: u32) -> usize {
+        (x + (y * self.width)) as usize
+    }
+}
+
+impl Console {
+    /// Sets the tile at `(x, y)`, if it's on the console, and stops it following the
+    /// palette.
+    pub fn put(&mut self, x: i32, y: i32, glyph: Glyph, fg: Color, bg: Color) {
+        if x < 0 || y < 0 {
+            return;
+        }
+        if let Some(tile) = self.tile_mut(x as u32, y as u32) {
+            tile.code_point = glyph;
+            tile.foreground = fg;
+            tile.background = bg;
+            tile.foreground_index = None;
+            tile.background_index = None;
+        }
+    }
+
+    /// Sets the tile at `(x, y)` with colours that follow `palette` as it's swapped
+    /// and cycled. Indices past the end of the palette are drawn black.
+    pub fn put_indexed(
+        &mut self,
+        x: i32,
+        y: i32,
+        glyph: Glyph,
+        fg: u16,
+        bg: u16,
+        palette: &Palette,
+    ) {
+        let black = Color::RGBA(0, 0, 0, 255);
+        self.put(
+            x,
+            y,
+            glyph,
+            palette.color(fg).unwrap_or(black),
+            palette.color(bg).unwrap_or(black),
+        );
+        if x < 0 || y < 0 {
+            return;
+        }
+        if let Some(tile) = self.tile_mut(x as u32, y as u32) {
+            tile.foreground_index = Some(fg);
+            tile.background_index = Some(bg);
+        }
+    }
+
+    /// Updates the colours of tiles that follow the palette, marking only the ones that
+    /// changed dirty.
+    pub fn recolor(&mut self, palette: &Palette) {
+        for index in 0..self.tiles.len() {
+            let tile = &self.tiles[index];
+            let fg = tile.foreground_index.and_then(|i| palette.color(i));
+            let bg = tile.background_index.and_then(|i| palette.color(i));
+            let fg = fg.filter(|&c| c != tile.foreground);
+            let bg = bg.filter(|&c| c != tile.background);
+            if fg.is_none() && bg.is_none() {
+                continue;
+            }
+            self.mark_dirty(index);
+            let tile = &mut self.tiles[index];
+            tile.foreground = fg.unwrap_or(tile.foreground);
+            tile.background = bg.unwrap_or(tile.background);
+        }
+    }
+
+    pub fn clear(&mut self, glyph: Glyph, fg: Color, bg: Color) {
+        let size = (self.width, self.height);
+        self.fill_rect((0, 0), size, glyph, fg, bg);
+    }
+
+    /// Prints `text` left to right from `(x, y)` without wrapping.
+    pub fn print(&mut self, x: i32, y: i32, text: &str, fg: Color, bg: Color) {
+        for (i, c) in text.chars().enumerate() {
+            self.put(x + i as i32, y, c.into(), fg, bg);
+        }
+    }
+
+    /// Prints `text` with colour tags, see `markup::parse`.
+    pub fn print_markup(
+        &mut self,
+        x: i32,
+        y: i32,
+        text: &str,
+        palette: &Palette,
+        fg: Color,
+        bg: Color,
+    ) -> Result<(), MarkupError> {
+        let mut x = x;
+        for span in markup::parse(text, palette, fg, bg)? {
+            self.print(x, y, &span.text, span.foreground, span.background);
+            x += span.text.chars().count() as i32;
+        }
+        Ok(())
+    }
+
+    pub fn fill_rect(
+        &mut self,
+        (x, y): (i32, i32),
+        (w, h): (u32, u32),
+        glyph: Glyph,
+        fg: Color,
+        bg: Color,
+    ) {
+        // In i64 so rects reaching past i32::MAX are clamped instead of wrapping
+        let (x, y) = (i64::from(x), i64::from(y));
+        let (x0, y0) = (x.max(0), y.max(0));
+        let x1 = (x + i64::from(w)).min(self.width.into());
+        let y1 = (y + i64::from(h)).min(self.height.into());
+        for ty in y0..y1 {
+            for tx in x0..x1 {
+                self.put(tx as i32, ty as i32, glyph, fg, bg);
+            }
+        }
+    }
+
+    pub fn hline(&mut self, x: i32, y: i32, len: u32, glyph: Glyph, fg: Color, bg: Color) {
+        self.fill_rect((x, y), (len, 1), glyph, fg, bg);
+    }
+
+    pub fn vline(&mut self, x: i32, y: i32, len: u32, glyph: Glyph, fg: Color, bg: Color) {
+        self.fill_rect((x, y), (1, len), glyph, fg, bg);
+    }
+
+    /// Draws the border of a `w` by `h` box, leaving its interior untouched.
+    pub fn draw_frame(
+        &mut self,
+        (x, y): (i32, i32),
+        (w, h): (u32, u32),
+        style: FrameStyle,
+        fg: Color,
+        bg: Color,
+    ) {
+        if w < 2 || h < 2 {
+            return;
+        }
+        let [top_left, top_right, bottom_right, bottom_left, horizontal, vertical] = style.glyphs();
+        // Edges clamped to i32::MAX are off the console either way
+        let far_edge = |start: i32, len: u32| {
+            (i64::from(start) + i64::from(len) - 1).min(i32::MAX.into()) as i32
+        };
+        let (x1, y1) = (far_edge(x, w), far_edge(y, h));
+        self.hline(x.saturating_add(1), y, w - 2, horizontal.into(), fg, bg);
+        self.hline(x.saturating_add(1), y1, w - 2, horizontal.into(), fg, bg);
+        self.vline(x, y.saturating_add(1), h - 2, vertical.into(), fg, bg);
+        self.vline(x1, y.saturating_add(1), h - 2, vertical.into(), fg, bg);
+        self.put(x, y, top_left.into(), fg, bg);
+        self.put(x1, y, top_right.into(), fg, bg);
+        self.put(x1, y1, bottom_right.into(), fg, bg);
+        self.put(x, y1, bottom_left.into(), fg, bg);
+    }
+
+    /// Draws a line from `(x0, y0)` to `(x1, y1)` inclusive using Bresenham's algorithm.
+    ///
+    /// The line is clipped to the console first, so only the part on it costs anything.
+    pub fn draw_line(
+        &mut self,
+        from: (i32, i32),
+        to: (i32, i32),
+        glyph: Glyph,
+        fg: Color,
+        bg: Color,
+    ) {
+        let ((mut x0, mut y0), (x1, y1)) = match self.clip_line(from, to) {
+            Some(ends) => ends,
+            None => return,
+        };
+        let dx = (x1 - x0).abs();
+        let dy = -(y1 - y0).abs();
+        let sx = if x0 < x1 { 1 } else { -1 };
+        let sy = if y0 < y1 { 1 } else { -1 };
+        let mut err = dx + dy;
+        loop {
+            self.put(x0 as i32, y0 as i32, glyph, fg, bg);
+            if x0 == x1 && y0 == y1 {
+                break;
+            }
+            let e2 = 2 * err;
+            if e2 >= dy {
+                err += dy;
+                x0 += sx;
+            }
+            if e2 <= dx {
+                err += dx;
+                y0 += sy;
+            }
+        }
+    }
+
+    // The part of the line from `from` to `to` on the console, by Liang-Barsky, with
+    // the ends rounded to cells
+    fn clip_line(&self, from: (i32, i32), to: (i32, i32)) -> Option<((i64, i64), (i64, i64))> {
+        if self.width == 0 || self.height == 0 {
+            return None;
+        }
+        let (x0, y0) = (from.0 as f64, from.1 as f64);
+        let (dx, dy) = (to.0 as f64 - x0, to.1 as f64 - y0);
+        let (max_x, max_y) = (self.width as f64 - 1.0, self.height as f64 - 1.0);
+        let (mut t0, mut t1) = (0.0f64, 1.0f64);
+        for &(p, q) in &[(-dx, x0), (dx, max_x - x0), (-dy, y0), (dy, max_y - y0)] {
+            if p == 0.0 {
+                // Parallel to this edge, and outside it
+                if q < 0.0 {
+                    return None;
+                }
+            } else if p < 0.0 {
+                t0 = t0.max(q / p);
+            } else {
+                t1 = t1.min(q / p);
+            }
+        }
+        if t0 > t1 {
+            return None;
+        }
+        let at = |t: f64| ((x0 + t * dx).round() as i64, (y0 + t * dy).round() as i64);
+        Some((at(t0), at(t1)))
+    }
+
+    /// Copies the `src_rect` area of `other`, or all of it, to `(dst_x, dst_y)`.
+    ///
+    /// Both areas are clipped to their consoles, and only destination tiles whose
+    /// appearance changes are marked dirty.
+    pub fn blit<R>(
+        &mut self,
+        other: &Console,
+        src_rect: R,
+        dst_x: i32,
+        dst_y: i32,
+        opts: BlitOptions,
+    ) where
+        R: Into<Option<Rect>>,
+    {
+        if other.width == 0 || other.height == 0 {
+            return;
+        }
+        let bounds = Rect::new(0, 0, other.width, other.height);
+        let src = match src_rect.into() {
+            Some(rect) => match rect.intersection(bounds) {
+                Some(rect) => rect,
+                None => return,
+            },
+            None => bounds,
+        };
+        for sy in src.top()..src.bottom() {
+            for sx in src.left()..src.right() {
+                let (x, y) = (dst_x + sx - src.x(), dst_y + sy - src.y());
+                if x < 0 || y < 0 || x >= self.width as i32 || y >= self.height as i32 {
+                    continue;
+                }
+                let above = &other.tiles[other.index(sx as u32, sy as u32)];
+                if opts.key_background == Some(above.background)
+                    || opts.key_glyph == Some(above.code_point)
+                {
+                    continue;
+                }
+                let tile = &self.tiles[self.index(x as u32, y as u32)];
+                let (glyph, fg, bg) = blend(
+                    (tile.code_point, tile.foreground, tile.background),
+                    above,
+                    opts.foreground_alpha,
+                    opts.background_alpha,
+                );
+                if tile.code_point != glyph || tile.foreground != fg || tile.background != bg {
+                    self.put(x, y, glyph, fg, bg);
+                }
+            }
+        }
+    }
+
+    /// Changes the size of the console, keeping tiles at the same coordinates and
+    /// filling any new ones. Every tile is marked dirty.
+    pub fn resize(&mut self, (width, height): (u32, u32), glyph: Glyph, fg: Color, bg: Color) {
+        let mut resized = Console::new(width, height);
+        resized.clear(glyph, fg, bg);
+        for tile in self.tiles.drain(..) {
+            if tile.row < width && tile.col < height {
+                let index = resized.index(tile.row, tile.col);
+                resized.tiles[index] = Tile {
+                    dirty: true,
+                    ..tile
+                };
+            }
+        }
+        *self = resized;
+    }
+
+    /// Writes the console as RON, one run of identical tiles per line so screens
+    /// and prefabs diff well.
+    pub fn save_ron(&self, path: &Path) -> Result<(), String> {
+        let config = ron::ser::PrettyConfig {
+            depth_limit: 3,
+            ..Default::default()
+        };
+        let text = ron::ser::to_string_pretty(self, config).map_err(|e| e.to_string())?;
+        fs::write(path, text).map_err(|e| format!("{}: {}", path.display(), e))
+    }
+
+    pub fn load_ron(path: &Path) -> Result<Self, String> {
+        let file = fs::File::open(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        ron::de::from_reader(file).map_err(|e| format!("{}: {}", path.display(), e))
+    }
+}
+
+/// Draws `above` over a tile made of `glyph`, `fg` and `bg`, scaling its alphas.
+///
+/// The background is blended by the background alpha, and a non-blank glyph replaces
+/// the glyph below it, blended over the new background by the foreground alpha.
+pub fn blend(
+    (glyph, fg, bg): (Glyph, Color, Color),
+    above: &Tile,
+    fg_alpha: f32,
+    bg_alpha: f32,
+) -> (Glyph, Color, Color) {
+    let bg_alpha = above.background.a as f32 / 255.0 * bg_alpha;
+    let fg_alpha = above.foreground.a as f32 / 255.0 * fg_alpha;
+    let new_bg = opaque(color::lerp(bg, above.background, bg_alpha));
+    if fg_alpha > 0.0 && !above.code_point.is_blank() {
+        let new_fg = opaque(color::lerp(new_bg, above.foreground, fg_alpha));
+        (above.code_point, new_fg, new_bg)
+    } else {
+        let new_fg = opaque(color::lerp(fg, above.background, bg_alpha));
+        (glyph, new_fg, new_bg)
+    }
+}
+
+fn opaque(c: Color) -> Color {
+    Color::RGBA(c.r, c.g, c.b, 255)
+}
+
+// Consecutive tiles in row-major order sharing the glyph, colours and animations
+#[derive(Serialize, Deserialize)]
+struct TileRun(
+    u32,
+    Glyph,
+    RunColor,
+    RunColor,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")] Vec<Animation>,
+);
+
+// A colour as (r, g, b, a), followed by the palette index it follows if it has one
+#[derive(Copy, Clone, PartialEq, Serialize, Deserialize)]
+struct RunColor(
+    u8,
+    u8,
+    u8,
+    u8,
+    #[serde(default, skip_serializing_if = "Option::is_none")] Option<u16>,
+);
+
+impl RunColor {
+    fn new(color: Color, index: Option<u16>) -> Self {
+        RunColor(color.r, color.g, color.b, color.a, index)
+    }
+
+    fn color(self) -> Color {
+        Color::RGBA(self.0, self.1, self.2, self.3)
+    }
+}
+
+// Bounds what a file can make `try_from` allocate, well past any saved screen or map
+const MAX_LOADED_TILES: u64 = 1 << 22;
+
+#[derive(Serialize, Deserialize)]
+struct ConsoleRepr {
+    width: u32,
+    height: u32,
+    runs: Vec<TileRun>,
+}
+
+impl From<Console> for ConsoleRepr {
+    fn from(console: Console) -> Self {
+        let mut runs: Vec<TileRun> = Vec::new();
+        for tile in console.tiles {
+            match runs.last_mut() {
+                Some(TileRun(count, glyph, fg, bg, animations))
+                    if *glyph == tile.code_point
+                        && *fg == RunColor::new(tile.foreground, tile.foreground_index)
+                        && *bg == RunColor::new(tile.background, tile.background_index)
+                        && *animations == tile.animations =>
+                {
+                    *count += 1
+                }
+                _ => runs.push(TileRun(
+                    1,
+                    tile.code_point,
+                    RunColor::new(tile.foreground, tile.foreground_index),
+                    RunColor::new(tile.background, tile.background_index),
+                    tile.animations,
+                )),
+            }
+        }
+        Self {
+            width: console.width,
+            height: console.height,
+            runs,
+        }
+    }
+}
+
+impl TryFrom<ConsoleRepr> for Console {
+    type Error = String;
+
+    fn try_from(repr: ConsoleRepr) -> Result<Self, String> {
+        let size = repr.width as u64 * repr.height as u64;
+        if size > MAX_LOADED_TILES {
+            return Err(format!(
+                "a {}x{} console is too big to load",
+                repr.width, repr.height
+            ));
+        }
+        let total: u64 = repr.runs.iter().map(|run| run.0 as u64).sum();
+        if total != size {
+            return Err(format!(
+                "runs cover {} tiles but a {}x{} console has {}",
+                total, repr.width, repr.height, size
+            ));
+        }
+        let mut console = Console::new(repr.width, repr.height);
+        let mut tiles = console.tiles.iter_mut();
+        for TileRun(count, glyph, fg, bg, animations) in repr.runs {
+            for tile in tiles.by_ref().take(count as usize) {
+                tile.code_point = glyph;
+                tile.foreground = fg.color();
+                tile.background = bg.color();
+                tile.foreground_index = fg.4;
+                tile.background_index = bg.4;
+                tile.animations = animations.clone();
+            }
+        }
+        Ok(console)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    type TileParts = (
+        Glyph,
+        Color,
+        Color,
+        Option<u16>,
+        Option<u16>,
+        Vec<Animation>,
+    );
+
+    fn parts(console: &Console) -> Vec<TileParts> {
+        console
+            .tiles()
+            .iter()
+            .map(|tile| {
+                (
+                    tile.code_point,
+                    tile.foreground,
+                    tile.background,
+                    tile.foreground_index,
+                    tile.background_index,
+                    tile.animations.clone(),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn round_trips_through_ron() {
+        let (fg, bg) = (Color::RGBA(10, 20, 30, 255), Color::RGBA(0, 0, 0, 0));
+        let mut console = Console::new(4, 2);
+        console.clear(Cp437::Space.into(), fg, bg);
+        console.put(0, 0, Cp437::Null.into(), fg, bg);
+        console.put(1, 0, Glyph::from_char('\u{7}'), fg, bg);
+        console.put(2, 0, Glyph(300), fg, bg);
+        console.put(3, 0, Glyph::from_char('λ'), fg, bg);
+        let tile = console.tile_mut(0, 1).unwrap();
+        tile.foreground_index = Some(3);
+        tile.background_index = Some(17);
+        tile.animations = vec![
+            Animation::Blink(0.5),
+            Animation::Keyframed {
+                name: "torch".to_string(),
+                start: 1.5,
+            },
+        ];
+
+        let path = std::env::temp_dir().join(format!("console-test-{}.ron", std::process::id()));
+        console.save_ron(&path).unwrap();
+        let text = fs::read_to_string(&path).unwrap();
+        let loaded = Console::load_ron(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert!(!text.chars().any(|c| c.is_control() && c != '\n'));
+        assert_eq!((loaded.width(), loaded.height()), (4, 2));
+        assert_eq!(parts(&loaded), parts(&console));
+    }
+
+    #[test]
+    fn rejects_runs_that_dont_cover_the_console() {
+        let text = "(width: 2, height: 2, runs: [(3, Cp437(' '), (0, 0, 0, 255), (0, 0, 0, 255))])";
+        let error = ron::de::from_str::<Console>(text).unwrap_err().to_string();
+        assert!(error.contains("runs cover 3 tiles"), "{}", error);
+    }
+
+    #[test]
+    fn rejects_huge_consoles_before_allocating() {
+        let text = "(width: 65536, height: 65536, runs: [\
+            (4294967295, Cp437(' '), (0, 0, 0, 255), (0, 0, 0, 255)), \
+            (1, Cp437(' '), (0, 0, 0, 255), (0, 0, 0, 255))])";
+        let error = ron::de::from_str::<Console>(text).unwrap_err().to_string();
+        assert!(error.contains("too big"), "{}", error);
+    }
+
+    // Cells holding `glyph`, in row-major order
+    fn cells_with(console: &Console, glyph: Glyph) -> Vec<(u32, u32)> {
+        console
+            .tiles()
+            .iter()
+            .filter(|tile| tile.code_point == glyph)
+            .map(|tile| (tile.row, tile.col))
+            .collect()
+    }
+
+    #[test]
+    fn clips_lines_to_the_console() {
+        let (fg, bg) = (Color::RGBA(255, 255, 255, 255), Color::RGBA(0, 0, 0, 255));
+        let hash = Cp437::NumberSign.into();
+        let mut console = Console::new(5, 4);
+        console.draw_line((-3, -3), (100, 100), hash, fg, bg);
+        assert_eq!(
+            cells_with(&console, hash),
+            vec![(0, 0), (1, 1), (2, 2), (3, 3)]
+        );
+
+        // Lines too long to walk cell by cell, and ones whose deltas overflow i32
+        let mut console = Console::new(5, 4);
+        console.draw_line((0, 1), (i32::MAX, 1), hash, fg, bg);
+        console.draw_line((i32::MIN, i32::MIN), (i32::MAX, i32::MAX), hash, fg, bg);
+        let mut expected = vec![(0, 0), (0, 1), (1, 1), (2, 1), (3, 1), (4, 1)];
+        expected.extend(&[(2, 2), (3, 3)]);
+        assert_eq!(cells_with(&console, hash), expected);
+    }
+
+    #[test]
+    fn skips_lines_off_the_console() {
+        let (fg, bg) = (Color::RGBA(255, 255, 255, 255), Color::RGBA(0, 0, 0, 255));
+        let hash = Cp437::NumberSign.into();
+        let mut console = Console::new(5, 4);
+        console.reset_tiles();
+        console.draw_line((-10, -1), (10, -1), hash, fg, bg);
+        console.draw_line((6, 0), (20, 3), hash, fg, bg);
+        console.draw_line((-5, 10), (10, 4), hash, fg, bg);
+        console.draw_line((i32::MIN, 0), (-1, i32::MAX), hash, fg, bg);
+        assert!(cells_with(&console, hash).is_empty());
+        assert!(!console.is_dirty());
+    }
+
+    // Dirty cells as `(x, y)`, in row-major order
+    fn dirty_cells(console: &Console) -> Vec<(u32, u32)> {
+        let mut cells: Vec<(u32, u32)> = console.dirty_tiles().map(|t| (t.row, t.col)).collect();
+        cells.sort_by_key(|&(x, y)| (y, x));
+        cells
+    }
+
+    #[test]
+    fn fills_only_the_rect() {
+        let (fg, bg) = (Color::RGBA(255, 255, 255, 255), Color::RGBA(0, 0, 0, 255));
+        let hash = Cp437::NumberSign.into();
+        let mut console = Console::new(5, 4);
+        console.reset_tiles();
+        console.fill_rect((1, 2), (2, 2), hash, fg, bg);
+        assert_eq!(dirty_cells(&console), vec![(1, 2), (2, 2), (1, 3), (2, 3)]);
+
+        // Sizes past i32::MAX and rects ending past it are clamped, not wrapped
+        console.reset_tiles();
+        console.fill_rect((3, 3), (u32::MAX, u32::MAX), hash, fg, bg);
+        console.fill_rect((i32::MAX, 0), (10, 10), hash, fg, bg);
+        console.fill_rect((i32::MIN, i32::MIN), (u32::MAX, 1), hash, fg, bg);
+        assert_eq!(dirty_cells(&console), vec![(3, 3), (4, 3)]);
+    }
+
+    #[test]
+    fn draws_frames_partly_off_the_console() {
+        let (fg, bg) = (Color::RGBA(255, 255, 255, 255), Color::RGBA(0, 0, 0, 255));
+        let mut console = Console::new(5, 4);
+        console.reset_tiles();
+        console.draw_frame((2, -1), (4, 4), FrameStyle::Single, fg, bg);
+        let glyph = |x, y| console.tile(x, y).unwrap().code_point;
+        assert_eq!(
+            dirty_cells(&console),
+            vec![(2, 0), (2, 1), (2, 2), (3, 2), (4, 2)]
+        );
+        assert_eq!(glyph(2, 0), Cp437::BoxDrawingsLightVertical.into());
+        assert_eq!(glyph(2, 2), Cp437::BoxDrawingsLightUpAndRight.into());
+        assert_eq!(glyph(3, 2), Cp437::BoxDrawingsLightHorizontal.into());
+
+        let mut console = Console::new(5, 4);
+        console.reset_tiles();
+        console.draw_frame(
+            (i32::MAX, i32::MAX),
+            (u32::MAX, 3),
+            FrameStyle::Double,
+            fg,
+            bg,
+        );
+        assert!(!console.is_dirty());
+
+        // Spans the console with its corners far off either side
+        console.draw_frame((i32::MIN, 0), (u32::MAX, 3), FrameStyle::Double, fg, bg);
+        let rows = dirty_cells(&console).into_iter().map(|(_, y)| y);
+        assert_eq!(rows.collect::<Vec<_>>(), vec![0, 0, 0, 0, 0, 2, 2, 2, 2, 2]);
+    }
+}
@@ -1,11 +1,12 @@
-use std::mem::transmute;
+use std::convert::TryFrom;
+use std::fmt;
 
-pub const WIDTH: i32 = 16;
-pub const HEIGHT: i32 = 16;
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
 
+/// Serialised as the Unicode character the glyph stands for, see `char_code`.
 #[repr(i32)]
 #[allow(dead_code)]
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub enum Cp437 {
     Null,
     WhiteSmilingFace,
@@ -282,10 +283,288 @@ pub enum Cp437 {
     Count,
 }
 
-impl From<char> for Cp437 {
-    fn from(c: char) -> Self {
-        match c {
-            // '' => Null,
+const GLYPHS: [Cp437; Cp437::Count as usize] = [
+    Cp437::Null,
+    Cp437::WhiteSmilingFace,
+    Cp437::BlackSmilingFace,
+    Cp437::BlackHeartSuit,
+    Cp437::BlackDiamondSuit,
+    Cp437::BlackClubSuit,
+    Cp437::BlackSpadeSuit,
+    Cp437::Bullet,
+    Cp437::InverseBullet,
+    Cp437::WhiteCircle,
+    Cp437::InverseWhiteCircle,
+    Cp437::MaleSign,
+    Cp437::FemaleSign,
+    Cp437::EighthNote,
+    Cp437::BeamedEighthNotes,
+    Cp437::WhiteSunWithRays,
+    Cp437::BlackRightPointingPointer,
+    Cp437::BlackLeftPointingPointer,
+    Cp437::UpDownArrow,
+    Cp437::DoubleExclamationMark,
+    Cp437::PilcrowSign,
+    Cp437::SectionSign,
+    Cp437::BlackRectangle,
+    Cp437::UpDownArrowWithBase,
+    Cp437::UpwardsArrow,
+    Cp437::DownwardsArrow,
+    Cp437::RightwardsArrow,
+    Cp437::LeftwardsArrow,
+    Cp437::RightAngle,
+    Cp437::LeftRightArrow,
+    Cp437::BlackUpPointingTriangle,
+    Cp437::BlackDownPointingTriangle,
+    Cp437::Space,
+    Cp437::ExclamationMark,
+    Cp437::QuotationMark,
+    Cp437::NumberSign,
+    Cp437::DollarSign,
+    Cp437::PercentSign,
+    Cp437::Ampersand,
+    Cp437::Apostrophe,
+    Cp437::LeftParenthesis,
+    Cp437::RightParenthesis,
+    Cp437::Asterisk,
+    Cp437::PlusSign,
+    Cp437::Comma,
+    Cp437::HyphenMinus,
+    Cp437::FullStop,
+    Cp437::Solidus,
+    Cp437::DigitZero,
+    Cp437::DigitOne,
+    Cp437::DigitTwo,
+    Cp437::DigitThree,
+    Cp437::DigitFour,
+    Cp437::DigitFive,
+    Cp437::DigitSix,
+    Cp437::DigitSeven,
+    Cp437::DigitEight,
+    Cp437::DigitNine,
+    Cp437::Colon,
+    Cp437::Semicolon,
+    Cp437::LessThanSign,
+    Cp437::EqualsSign,
+    Cp437::GreaterThanSign,
+    Cp437::QuestionMark,
+    Cp437::CommercialAt,
+    Cp437::LatinCapitalLetterA,
+    Cp437::LatinCapitalLetterB,
+    Cp437::LatinCapitalLetterC,
+    Cp437::LatinCapitalLetterD,
+    Cp437::LatinCapitalLetterE,
+    Cp437::LatinCapitalLetterF,
+    Cp437::LatinCapitalLetterG,
+    Cp437::LatinCapitalLetterH,
+    Cp437::LatinCapitalLetterI,
+    Cp437::LatinCapitalLetterJ,
+    Cp437::LatinCapitalLetterK,
+    Cp437::LatinCapitalLetterL,
+    Cp437::LatinCapitalLetterM,
+    Cp437::LatinCapitalLetterN,
+    Cp437::LatinCapitalLetterO,
+    Cp437::LatinCapitalLetterP,
+    Cp437::LatinCapitalLetterQ,
+    Cp437::LatinCapitalLetterR,
+    Cp437::LatinCapitalLetterS,
+    Cp437::LatinCapitalLetterT,
+    Cp437::LatinCapitalLetterU,
+    Cp437::LatinCapitalLetterV,
+    Cp437::LatinCapitalLetterW,
+    Cp437::LatinCapitalLetterX,
+    Cp437::LatinCapitalLetterY,
+    Cp437::LatinCapitalLetterZ,
+    Cp437::LeftSquareBracket,
+    Cp437::ReverseSolidus,
+    Cp437::RightSquareBracket,
+    Cp437::CircumflexAccent,
+    Cp437::LowLine,
+    Cp437::GraveAccent,
+    Cp437::LatinSmallLetterA,
+    Cp437::LatinSmallLetterB,
+    Cp437::LatinSmallLetterC,
+    Cp437::LatinSmallLetterD,
+    Cp437::LatinSmallLetterE,
+    Cp437::LatinSmallLetterF,
+    Cp437::LatinSmallLetterG,
+    Cp437::LatinSmallLetterH,
+    Cp437::LatinSmallLetterI,
+    Cp437::LatinSmallLetterJ,
+    Cp437::LatinSmallLetterK,
+    Cp437::LatinSmallLetterL,
+    Cp437::LatinSmallLetterM,
+    Cp437::LatinSmallLetterN,
+    Cp437::LatinSmallLetterO,
+    Cp437::LatinSmallLetterP,
+    Cp437::LatinSmallLetterQ,
+    Cp437::LatinSmallLetterR,
+    Cp437::LatinSmallLetterS,
+    Cp437::LatinSmallLetterT,
+    Cp437::LatinSmallLetterU,
+    Cp437::LatinSmallLetterV,
+    Cp437::LatinSmallLetterW,
+    Cp437::LatinSmallLetterX,
+    Cp437::LatinSmallLetterY,
+    Cp437::LatinSmallLetterZ,
+    Cp437::LeftCurlyBracket,
+    Cp437::VerticalLine,
+    Cp437::RightCurlyBracket,
+    Cp437::Tilde,
+    Cp437::House,
+    Cp437::LatinCapitalLetterCWithCedilla,
+    Cp437::LatinSmallLetterUWithDiaeresis,
+    Cp437::LatinSmallLetterEWithAcute,
+    Cp437::LatinSmallLetterAWithCircumflex,
+    Cp437::LatinSmallLetterAWithDiaeresis,
+    Cp437::LatinSmallLetterAWithGrave,
+    Cp437::LatinSmallLetterAWithRingAbove,
+    Cp437::LatinSmallLetterCWithCedilla,
+    Cp437::LatinSmallLetterEWithCircumflex,
+    Cp437::LatinSmallLetterEWithDiaeresis,
+    Cp437::LatinSmallLetterEWithGrave,
+    Cp437::LatinSmallLetterIWithDiaeresis,
+    Cp437::LatinSmallLetterIWithCircumflex,
+    Cp437::LatinSmallLetterIWithGrave,
+    Cp437::LatinCapitalLetterAWithDiaeresis,
+    Cp437::LatinCapitalLetterAWithRingAbove,
+    Cp437::LatinCapitalLetterEWithAcute,
+    Cp437::LatinSmallLetterAe,
+    Cp437::LatinCapitalLetterAe,
+    Cp437::LatinSmallLetterOWithCircumflex,
+    Cp437::LatinSmallLetterOWithDiaeresis,
+    Cp437::LatinSmallLetterOWithGrave,
+    Cp437::LatinSmallLetterUWithCircumflex,
+    Cp437::LatinSmallLetterUWithGrave,
+    Cp437::LatinSmallLetterYWithDiaeresis,
+    Cp437::LatinCapitalLetterOWithDiaeresis,
+    Cp437::LatinCapitalLetterUWithDiaeresis,
+    Cp437::CentSign,
+    Cp437::PoundSign,
+    Cp437::YenSign,
+    Cp437::PesetaSign,
+    Cp437::LatinSmallLetterFWithHook,
+    Cp437::LatinSmallLetterAWithAcute,
+    Cp437::LatinSmallLetterIWithAcute,
+    Cp437::LatinSmallLetterOWithAcute,
+    Cp437::LatinSmallLetterUWithAcute,
+    Cp437::LatinSmallLetterNWithTilde,
+    Cp437::LatinCapitalLetterNWithTilde,
+    Cp437::FeminineOrdinalIndicator,
+    Cp437::MasculineOrdinalIndicator,
+    Cp437::InvertedQuestionMark,
+    Cp437::ReversedNotSign,
+    Cp437::NotSign,
+    Cp437::VulgarFractionOneHalf,
+    Cp437::VulgarFractionOneQuarter,
+    Cp437::InvertedExclamationMark,
+    Cp437::LeftPointingDoubleAngleQuotationMark,
+    Cp437::RightPointingDoubleAngleQuotationMark,
+    Cp437::LightShade,
+    Cp437::MediumShade,
+    Cp437::DarkShade,
+    Cp437::BoxDrawingsLightVertical,
+    Cp437::BoxDrawingsLightVerticalAndLeft,
+    Cp437::BoxDrawingsVerticalSingleAndLeftDouble,
+    Cp437::BoxDrawingsVerticalDoubleAndLeftSingle,
+    Cp437::BoxDrawingsDownDoubleAndLeftSingle,
+    Cp437::BoxDrawingsDownSingleAndLeftDouble,
+    Cp437::BoxDrawingsDoubleVerticalAndLeft,
+    Cp437::BoxDrawingsDoubleVertical,
+    Cp437::BoxDrawingsDoubleDownAndLeft,
+    Cp437::BoxDrawingsDoubleUpAndLeft,
+    Cp437::BoxDrawingsUpDoubleAndLeftSingle,
+    Cp437::BoxDrawingsUpSingleAndLeftDouble,
+    Cp437::BoxDrawingsLightDownAndLeft,
+    Cp437::BoxDrawingsLightUpAndRight,
+    Cp437::BoxDrawingsLightUpAndHorizontal,
+    Cp437::BoxDrawingsLightDownAndHorizontal,
+    Cp437::BoxDrawingsLightVerticalAndRight,
+    Cp437::BoxDrawingsLightHorizontal,
+    Cp437::BoxDrawingsLightVerticalAndHorizontal,
+    Cp437::BoxDrawingsVerticalSingleAndRightDouble,
+    Cp437::BoxDrawingsVerticalDoubleAndRightSingle,
+    Cp437::BoxDrawingsDoubleUpAndRight,
+    Cp437::BoxDrawingsDoubleDownAndRight,
+    Cp437::BoxDrawingsDoubleUpAndHorizontal,
+    Cp437::BoxDrawingsDoubleDownAndHorizontal,
+    Cp437::BoxDrawingsDoubleVerticalAndRight,
+    Cp437::BoxDrawingsDoubleHorizontal,
+    Cp437::BoxDrawingsDoubleVerticalAndHorizontal,
+    Cp437::BoxDrawingsUpSingleAndHorizontalDouble,
+    Cp437::BoxDrawingsUpDoubleAndHorizontalSingle,
+    Cp437::BoxDrawingsDownSingleAndHorizontalDouble,
+    Cp437::BoxDrawingsDownDoubleAndHorizontalSingle,
+    Cp437::BoxDrawingsUpDoubleAndRightSingle,
+    Cp437::BoxDrawingsUpSingleAndRightDouble,
+    Cp437::BoxDrawingsDownSingleAndRightDouble,
+    Cp437::BoxDrawingsDownDoubleAndRightSingle,
+    Cp437::BoxDrawingsVerticalDoubleAndHorizontalSingle,
+    Cp437::BoxDrawingsVerticalSingleAndHorizontalDouble,
+    Cp437::BoxDrawingsLightUpAndLeft,
+    Cp437::BoxDrawingsLightDownAndRight,
+    Cp437::FullBlock,
+    Cp437::LowerHalfBlock,
+    Cp437::LeftHalfBlock,
+    Cp437::RightHalfBlock,
+    Cp437::UpperHalfBlock,
+    Cp437::GreekSmallLetterAlpha,
+    Cp437::LatinSmallLetterSharpS,
+    Cp437::GreekCapitalLetterGamma,
+    Cp437::GreekSmallLetterPi,
+    Cp437::GreekCapitalLetterSigma,
+    Cp437::GreekSmallLetterSigma,
+    Cp437::MicroSign,
+    Cp437::GreekSmallLetterTau,
+    Cp437::GreekCapitalLetterPhi,
+    Cp437::GreekCapitalLetterTheta,
+    Cp437::GreekCapitalLetterOmega,
+    Cp437::GreekSmallLetterDelta,
+    Cp437::Infinity,
+    Cp437::GreekSmallLetterPhi,
+    Cp437::GreekSmallLetterEpsilon,
+    Cp437::Intersection,
+    Cp437::IdenticalTo,
+    Cp437::PlusMinusSign,
+    Cp437::GreaterThanOrEqualTo,
+    Cp437::LessThanOrEqualTo,
+    Cp437::TopHalfIntegral,
+    Cp437::BottomHalfIntegral,
+    Cp437::DivisionSign,
+    Cp437::AlmostEqualTo,
+    Cp437::DegreeSign,
+    Cp437::BulletOperator,
+    Cp437::MiddleDot,
+    Cp437::SquareRoot,
+    Cp437::SuperscriptLatinSmallLetterN,
+    Cp437::SuperscriptTwo,
+    Cp437::BlackSquare,
+    Cp437::NoBreakSpace,
+];
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Cp437Error {
+    OutOfRange(u32),
+    Unmapped(char),
+}
+
+impl fmt::Display for Cp437Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Cp437Error::OutOfRange(u) => write!(f, "{} is not a CP437 code point", u),
+            Cp437Error::Unmapped(c) => write!(f, "{:?} has no CP437 glyph", c),
+        }
+    }
+}
+
+impl std::error::Error for Cp437Error {}
+
+impl TryFrom<char> for Cp437 {
+    type Error = Cp437Error;
+
+    fn try_from(c: char) -> Result<Self, Self::Error> {
+        Ok(match c {
+            '\0' => Cp437::Null,
             '☺' => Cp437::WhiteSmilingFace,
             '☻' => Cp437::BlackSmilingFace,
             '♥' => Cp437::BlackHeartSuit,
@@ -556,39 +835,425 @@ impl From<char> for Cp437 {
             '²' => Cp437::SuperscriptTwo,
             '■' => Cp437::BlackSquare,
 
-            // '' => Cp437::// NoBreakSpace,
-            _ => Cp437::Null,
+            '\u{a0}' => Cp437::NoBreakSpace,
+            _ => return Err(Cp437Error::Unmapped(c)),
+        })
+    }
+}
+
+impl Cp437 {
+    /// Iterates over all 256 glyphs in code point order.
+    pub fn all() -> impl Iterator<Item = Cp437> {
+        GLYPHS.iter().copied()
+    }
+
+    pub fn to_char(self) -> char {
+        match self {
+            Cp437::Null => '\0',
+            Cp437::WhiteSmilingFace => '☺',
+            Cp437::BlackSmilingFace => '☻',
+            Cp437::BlackHeartSuit => '♥',
+            Cp437::BlackDiamondSuit => '♦',
+            Cp437::BlackClubSuit => '♣',
+            Cp437::BlackSpadeSuit => '♠',
+            Cp437::Bullet => '•',
+            Cp437::InverseBullet => '◘',
+            Cp437::WhiteCircle => '○',
+            Cp437::InverseWhiteCircle => '◙',
+            Cp437::MaleSign => '♂',
+            Cp437::FemaleSign => '♀',
+            Cp437::EighthNote => '♪',
+            Cp437::BeamedEighthNotes => '♫',
+            Cp437::WhiteSunWithRays => '☼',
+
+            Cp437::BlackRightPointingPointer => '►',
+            Cp437::BlackLeftPointingPointer => '◄',
+            Cp437::UpDownArrow => '↕',
+            Cp437::DoubleExclamationMark => '‼',
+            Cp437::PilcrowSign => '¶',
+            Cp437::SectionSign => '§',
+            Cp437::BlackRectangle => '▬',
+            Cp437::UpDownArrowWithBase => '↨',
+            Cp437::UpwardsArrow => '↑',
+            Cp437::DownwardsArrow => '↓',
+            Cp437::RightwardsArrow => '→',
+            Cp437::LeftwardsArrow => '←',
+            Cp437::RightAngle => '∟',
+            Cp437::LeftRightArrow => '↔',
+            Cp437::BlackUpPointingTriangle => '▲',
+            Cp437::BlackDownPointingTriangle => '▼',
+
+            Cp437::Space => ' ',
+            Cp437::ExclamationMark => '!',
+            Cp437::QuotationMark => '"',
+            Cp437::NumberSign => '#',
+            Cp437::DollarSign => '$',
+            Cp437::PercentSign => '%',
+            Cp437::Ampersand => '&',
+            Cp437::Apostrophe => '\'',
+            Cp437::LeftParenthesis => '(',
+            Cp437::RightParenthesis => ')',
+            Cp437::Asterisk => '*',
+            Cp437::PlusSign => '+',
+            Cp437::Comma => ',',
+            Cp437::HyphenMinus => '-',
+            Cp437::FullStop => '.',
+            Cp437::Solidus => '/',
+
+            Cp437::DigitZero => '0',
+            Cp437::DigitOne => '1',
+            Cp437::DigitTwo => '2',
+            Cp437::DigitThree => '3',
+            Cp437::DigitFour => '4',
+            Cp437::DigitFive => '5',
+            Cp437::DigitSix => '6',
+            Cp437::DigitSeven => '7',
+            Cp437::DigitEight => '8',
+            Cp437::DigitNine => '9',
+            Cp437::Colon => ':',
+            Cp437::Semicolon => ';',
+            Cp437::LessThanSign => '<',
+            Cp437::EqualsSign => '=',
+            Cp437::GreaterThanSign => '>',
+            Cp437::QuestionMark => '?',
+
+            Cp437::CommercialAt => '@',
+            Cp437::LatinCapitalLetterA => 'A',
+            Cp437::LatinCapitalLetterB => 'B',
+            Cp437::LatinCapitalLetterC => 'C',
+            Cp437::LatinCapitalLetterD => 'D',
+            Cp437::LatinCapitalLetterE => 'E',
+            Cp437::LatinCapitalLetterF => 'F',
+            Cp437::LatinCapitalLetterG => 'G',
+            Cp437::LatinCapitalLetterH => 'H',
+            Cp437::LatinCapitalLetterI => 'I',
+            Cp437::LatinCapitalLetterJ => 'J',
+            Cp437::LatinCapitalLetterK => 'K',
+            Cp437::LatinCapitalLetterL => 'L',
+            Cp437::LatinCapitalLetterM => 'M',
+            Cp437::LatinCapitalLetterN => 'N',
+            Cp437::LatinCapitalLetterO => 'O',
+
+            Cp437::LatinCapitalLetterP => 'P',
+            Cp437::LatinCapitalLetterQ => 'Q',
+            Cp437::LatinCapitalLetterR => 'R',
+            Cp437::LatinCapitalLetterS => 'S',
+            Cp437::LatinCapitalLetterT => 'T',
+            Cp437::LatinCapitalLetterU => 'U',
+            Cp437::LatinCapitalLetterV => 'V',
+            Cp437::LatinCapitalLetterW => 'W',
+            Cp437::LatinCapitalLetterX => 'X',
+            Cp437::LatinCapitalLetterY => 'Y',
+            Cp437::LatinCapitalLetterZ => 'Z',
+            Cp437::LeftSquareBracket => '[',
+            Cp437::ReverseSolidus => '\\',
+            Cp437::RightSquareBracket => ']',
+            Cp437::CircumflexAccent => '^',
+            Cp437::LowLine => '_',
+
+            Cp437::GraveAccent => '`',
+            Cp437::LatinSmallLetterA => 'a',
+            Cp437::LatinSmallLetterB => 'b',
+            Cp437::LatinSmallLetterC => 'c',
+            Cp437::LatinSmallLetterD => 'd',
+            Cp437::LatinSmallLetterE => 'e',
+            Cp437::LatinSmallLetterF => 'f',
+            Cp437::LatinSmallLetterG => 'g',
+            Cp437::LatinSmallLetterH => 'h',
+            Cp437::LatinSmallLetterI => 'i',
+            Cp437::LatinSmallLetterJ => 'j',
+            Cp437::LatinSmallLetterK => 'k',
+            Cp437::LatinSmallLetterL => 'l',
+            Cp437::LatinSmallLetterM => 'm',
+            Cp437::LatinSmallLetterN => 'n',
+            Cp437::LatinSmallLetterO => 'o',
+
+            Cp437::LatinSmallLetterP => 'p',
+            Cp437::LatinSmallLetterQ => 'q',
+            Cp437::LatinSmallLetterR => 'r',
+            Cp437::LatinSmallLetterS => 's',
+            Cp437::LatinSmallLetterT => 't',
+            Cp437::LatinSmallLetterU => 'u',
+            Cp437::LatinSmallLetterV => 'v',
+            Cp437::LatinSmallLetterW => 'w',
+            Cp437::LatinSmallLetterX => 'x',
+            Cp437::LatinSmallLetterY => 'y',
+            Cp437::LatinSmallLetterZ => 'z',
+            Cp437::LeftCurlyBracket => '{',
+            Cp437::VerticalLine => '|',
+            Cp437::RightCurlyBracket => '}',
+            Cp437::Tilde => '~',
+            Cp437::House => '⌂',
+
+            Cp437::LatinCapitalLetterCWithCedilla => 'Ç',
+            Cp437::LatinSmallLetterUWithDiaeresis => 'ü',
+            Cp437::LatinSmallLetterEWithAcute => 'é',
+            Cp437::LatinSmallLetterAWithCircumflex => 'â',
+            Cp437::LatinSmallLetterAWithDiaeresis => 'ä',
+            Cp437::LatinSmallLetterAWithGrave => 'à',
+            Cp437::LatinSmallLetterAWithRingAbove => 'å',
+            Cp437::LatinSmallLetterCWithCedilla => 'ç',
+            Cp437::LatinSmallLetterEWithCircumflex => 'ê',
+            Cp437::LatinSmallLetterEWithDiaeresis => 'ë',
+            Cp437::LatinSmallLetterEWithGrave => 'è',
+            Cp437::LatinSmallLetterIWithDiaeresis => 'ï',
+            Cp437::LatinSmallLetterIWithCircumflex => 'î',
+            Cp437::LatinSmallLetterIWithGrave => 'ì',
+            Cp437::LatinCapitalLetterAWithDiaeresis => 'Ä',
+            Cp437::LatinCapitalLetterAWithRingAbove => 'Å',
+
+            Cp437::LatinCapitalLetterEWithAcute => 'É',
+            Cp437::LatinSmallLetterAe => 'æ',
+            Cp437::LatinCapitalLetterAe => 'Æ',
+            Cp437::LatinSmallLetterOWithCircumflex => 'ô',
+            Cp437::LatinSmallLetterOWithDiaeresis => 'ö',
+            Cp437::LatinSmallLetterOWithGrave => 'ò',
+            Cp437::LatinSmallLetterUWithCircumflex => 'û',
+            Cp437::LatinSmallLetterUWithGrave => 'ù',
+            Cp437::LatinSmallLetterYWithDiaeresis => 'ÿ',
+            Cp437::LatinCapitalLetterOWithDiaeresis => 'Ö',
+            Cp437::LatinCapitalLetterUWithDiaeresis => 'Ü',
+            Cp437::CentSign => '¢',
+            Cp437::PoundSign => '£',
+            Cp437::YenSign => '¥',
+            Cp437::PesetaSign => '₧',
+            Cp437::LatinSmallLetterFWithHook => 'ƒ',
+
+            Cp437::LatinSmallLetterAWithAcute => 'á',
+            Cp437::LatinSmallLetterIWithAcute => 'í',
+            Cp437::LatinSmallLetterOWithAcute => 'ó',
+            Cp437::LatinSmallLetterUWithAcute => 'ú',
+            Cp437::LatinSmallLetterNWithTilde => 'ñ',
+            Cp437::LatinCapitalLetterNWithTilde => 'Ñ',
+            Cp437::FeminineOrdinalIndicator => 'ª',
+            Cp437::MasculineOrdinalIndicator => 'º',
+            Cp437::InvertedQuestionMark => '¿',
+            Cp437::ReversedNotSign => '⌐',
+            Cp437::NotSign => '¬',
+            Cp437::VulgarFractionOneHalf => '½',
+            Cp437::VulgarFractionOneQuarter => '¼',
+            Cp437::InvertedExclamationMark => '¡',
+            Cp437::LeftPointingDoubleAngleQuotationMark => '«',
+            Cp437::RightPointingDoubleAngleQuotationMark => '»',
+
+            Cp437::LightShade => '░',
+            Cp437::MediumShade => '▒',
+            Cp437::DarkShade => '▓',
+            Cp437::BoxDrawingsLightVertical => '│',
+            Cp437::BoxDrawingsLightVerticalAndLeft => '┤',
+            Cp437::BoxDrawingsVerticalSingleAndLeftDouble => '╡',
+            Cp437::BoxDrawingsVerticalDoubleAndLeftSingle => '╢',
+            Cp437::BoxDrawingsDownDoubleAndLeftSingle => '╖',
+            Cp437::BoxDrawingsDownSingleAndLeftDouble => '╕',
+            Cp437::BoxDrawingsDoubleVerticalAndLeft => '╣',
+            Cp437::BoxDrawingsDoubleVertical => '║',
+            Cp437::BoxDrawingsDoubleDownAndLeft => '╗',
+            Cp437::BoxDrawingsDoubleUpAndLeft => '╝',
+            Cp437::BoxDrawingsUpDoubleAndLeftSingle => '╜',
+            Cp437::BoxDrawingsUpSingleAndLeftDouble => '╛',
+            Cp437::BoxDrawingsLightDownAndLeft => '┐',
+
+            Cp437::BoxDrawingsLightUpAndRight => '└',
+            Cp437::BoxDrawingsLightUpAndHorizontal => '┴',
+            Cp437::BoxDrawingsLightDownAndHorizontal => '┬',
+            Cp437::BoxDrawingsLightVerticalAndRight => '├',
+            Cp437::BoxDrawingsLightHorizontal => '─',
+            Cp437::BoxDrawingsLightVerticalAndHorizontal => '┼',
+            Cp437::BoxDrawingsVerticalSingleAndRightDouble => '╞',
+            Cp437::BoxDrawingsVerticalDoubleAndRightSingle => '╟',
+            Cp437::BoxDrawingsDoubleUpAndRight => '╚',
+            Cp437::BoxDrawingsDoubleDownAndRight => '╔',
+            Cp437::BoxDrawingsDoubleUpAndHorizontal => '╩',
+            Cp437::BoxDrawingsDoubleDownAndHorizontal => '╦',
+            Cp437::BoxDrawingsDoubleVerticalAndRight => '╠',
+            Cp437::BoxDrawingsDoubleHorizontal => '═',
+            Cp437::BoxDrawingsDoubleVerticalAndHorizontal => '╬',
+            Cp437::BoxDrawingsUpSingleAndHorizontalDouble => '╧',
+
+            Cp437::BoxDrawingsUpDoubleAndHorizontalSingle => '╨',
+            Cp437::BoxDrawingsDownSingleAndHorizontalDouble => '╤',
+            Cp437::BoxDrawingsDownDoubleAndHorizontalSingle => '╥',
+            Cp437::BoxDrawingsUpDoubleAndRightSingle => '╙',
+            Cp437::BoxDrawingsUpSingleAndRightDouble => '╘',
+            Cp437::BoxDrawingsDownSingleAndRightDouble => '╒',
+            Cp437::BoxDrawingsDownDoubleAndRightSingle => '╓',
+            Cp437::BoxDrawingsVerticalDoubleAndHorizontalSingle => '╫',
+            Cp437::BoxDrawingsVerticalSingleAndHorizontalDouble => '╪',
+            Cp437::BoxDrawingsLightUpAndLeft => '┘',
+            Cp437::BoxDrawingsLightDownAndRight => '┌',
+            Cp437::FullBlock => '█',
+            Cp437::LowerHalfBlock => '▄',
+            Cp437::LeftHalfBlock => '▌',
+            Cp437::RightHalfBlock => '▐',
+            Cp437::UpperHalfBlock => '▀',
+
+            Cp437::GreekSmallLetterAlpha => 'α',
+            Cp437::LatinSmallLetterSharpS => 'ß',
+            Cp437::GreekCapitalLetterGamma => 'Γ',
+            Cp437::GreekSmallLetterPi => 'π',
+            Cp437::GreekCapitalLetterSigma => 'Σ',
+            Cp437::GreekSmallLetterSigma => 'σ',
+            Cp437::MicroSign => 'µ',
+            Cp437::GreekSmallLetterTau => 'τ',
+            Cp437::GreekCapitalLetterPhi => 'Φ',
+            Cp437::GreekCapitalLetterTheta => 'Θ',
+            Cp437::GreekCapitalLetterOmega => 'Ω',
+            Cp437::GreekSmallLetterDelta => 'δ',
+            Cp437::Infinity => '∞',
+            Cp437::GreekSmallLetterPhi => 'φ',
+            Cp437::GreekSmallLetterEpsilon => 'ε',
+            Cp437::Intersection => '∩',
+
+            Cp437::IdenticalTo => '≡',
+            Cp437::PlusMinusSign => '±',
+            Cp437::GreaterThanOrEqualTo => '≥',
+            Cp437::LessThanOrEqualTo => '≤',
+            Cp437::TopHalfIntegral => '⌠',
+            Cp437::BottomHalfIntegral => '⌡',
+            Cp437::DivisionSign => '÷',
+            Cp437::AlmostEqualTo => '≈',
+            Cp437::DegreeSign => '°',
+            Cp437::BulletOperator => '∙',
+            Cp437::MiddleDot => '·',
+            Cp437::SquareRoot => '√',
+            Cp437::SuperscriptLatinSmallLetterN => 'ⁿ',
+            Cp437::SuperscriptTwo => '²',
+            Cp437::BlackSquare => '■',
+            Cp437::NoBreakSpace => '\u{a0}',
+
+            Cp437::Count => '\0',
         }
     }
 }
 
-impl From<u32> for Cp437 {
-    fn from(u: u32) -> Self {
-        if u >= Cp437::Count as u32 {
-            Cp437::Null
+impl Serialize for Cp437 {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        char_code::serialize(&self.to_char(), serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Cp437 {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let c = char_code::deserialize(deserializer)?;
+        Cp437::try_from(c).map_err(de::Error::custom)
+    }
+}
+
+/// Serialises characters as themselves, or as their code point if they are control
+/// characters, so files holding them stay text. Either form is read back.
+pub mod char_code {
+    use std::fmt;
+
+    use serde::de::{self, Visitor};
+    use serde::{Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(c: &char, serializer: S) -> Result<S::Ok, S::Error> {
+        if c.is_control() {
+            serializer.serialize_u32(*c as u32)
         } else {
-            unsafe { transmute(u) }
+            serializer.serialize_char(*c)
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<char, D::Error> {
+        deserializer.deserialize_any(CharCode)
+    }
+
+    struct CharCode;
+
+    impl<'de> Visitor<'de> for CharCode {
+        type Value = char;
+
+        fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            write!(f, "a character or a code point")
+        }
+
+        fn visit_char<E: de::Error>(self, c: char) -> Result<char, E> {
+            Ok(c)
+        }
+
+        fn visit_str<E: de::Error>(self, s: &str) -> Result<char, E> {
+            let mut chars = s.chars();
+            match (chars.next(), chars.next()) {
+                (Some(c), None) => Ok(c),
+                _ => Err(E::invalid_value(de::Unexpected::Str(s), &self)),
+            }
+        }
+
+        fn visit_u64<E: de::Error>(self, n: u64) -> Result<char, E> {
+            std::char::from_u32(n as u32)
+                .filter(|_| n <= u32::MAX as u64)
+                .ok_or_else(|| E::invalid_value(de::Unexpected::Unsigned(n), &self))
+        }
+
+        fn visit_i64<E: de::Error>(self, n: i64) -> Result<char, E> {
+            if n < 0 {
+                return Err(E::invalid_value(de::Unexpected::Signed(n), &self));
+            }
+            self.visit_u64(n as u64)
         }
     }
 }
 
-#[derive(Debug, Copy, Clone)]
-pub struct Coords {
-    pub row: i32,
-    pub col: i32,
+impl From<Cp437> for char {
+    fn from(cp: Cp437) -> Self {
+        cp.to_char()
+    }
+}
+
+/// Characters without a CP437 glyph are encoded as `Cp437::Null`.
+#[allow(dead_code)]
+pub fn encode_str(s: &str) -> Vec<Cp437> {
+    s.chars()
+        .map(|c| Cp437::try_from(c).unwrap_or(Cp437::Null))
+        .collect()
+}
+
+#[allow(dead_code)]
+pub fn decode(cps: &[Cp437]) -> String {
+    cps.iter().map(|cp| cp.to_char()).collect()
 }
 
-impl Coords {
-    pub fn new(row: i32, col: i32) -> Self {
-        Self { row, col }
+impl TryFrom<u32> for Cp437 {
+    type Error = Cp437Error;
+
+    fn try_from(u: u32) -> Result<Self, Self::Error> {
+        GLYPHS
+            .get(u as usize)
+            .copied()
+            .ok_or(Cp437Error::OutOfRange(u))
     }
 }
 
-impl From<Cp437> for Coords {
-    fn from(cp: Cp437) -> Self {
-        Self {
-            row: cp as i32 % WIDTH,
-            col: cp as i32 / HEIGHT,
+// Every byte is a valid code point, so this also provides an infallible `TryFrom<u8>`.
+impl From<u8> for Cp437 {
+    fn from(b: u8) -> Self {
+        GLYPHS[b as usize]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn all_glyphs_round_trip() {
+        let glyphs: Vec<Cp437> = Cp437::all().collect();
+        assert_eq!(glyphs.len(), 256);
+        for (i, &cp) in glyphs.iter().enumerate() {
+            assert_eq!(Cp437::try_from(cp.to_char()), Ok(cp));
+            assert_eq!(Cp437::from(i as u8), cp);
         }
+        assert_eq!(encode_str(&decode(&glyphs)), glyphs);
+    }
+
+    #[test]
+    fn serialises_as_text() {
+        let glyphs: Vec<Cp437> = Cp437::all().collect();
+        let text = ron::ser::to_string(&glyphs).unwrap();
+        assert!(!text.chars().any(char::is_control));
+        assert!(text.starts_with("[0,'☺',"));
+        let back: Vec<Cp437> = ron::de::from_str(&text).unwrap();
+        assert_eq!(back, glyphs);
     }
 }
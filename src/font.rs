@@ -0,0 +1,120 @@
+use std::collections::HashMap;
+use std::path::Path;
+
+use sdl2::pixels::{Color, PixelFormatEnum};
+use sdl2::rect::Rect;
+use sdl2::render::{BlendMode, Texture, TextureCreator};
+use sdl2::surface::Surface;
+use sdl2::ttf::{Font, Sdl2TtfContext};
+use sdl2::video::WindowContext;
+
+const ATLAS_CELLS: (u32, u32) = (32, 32);
+
+/// Rasterises TrueType glyphs on demand into a texture of console-sized cells.
+pub struct FontAtlas<'ttf, 'r> {
+    font: Font<'ttf, 'static>,
+    texture: Texture<'r>,
+    cell_size: (u32, u32),
+    cells: HashMap<char, Option<Rect>>,
+    next: u32,
+}
+
+impl<'ttf, 'r> FontAtlas<'ttf, 'r> {
+    pub fn new(
+        ttf_context: &'ttf Sdl2TtfContext,
+        texture_creator: &'r TextureCreator<WindowContext>,
+        path: &Path,
+        cell_size: (u32, u32),
+    ) -> Result<Self, String> {
+        // Use the largest point size whose line height still fits in a cell
+        let mut point_size = cell_size.1 as u16;
+        let font = loop {
+            let font = ttf_context.load_font(path, point_size)?;
+            if font.height() <= cell_size.1 as i32 || point_size <= 1 {
+                break font;
+            }
+            point_size -= 1;
+        };
+        let mut texture = texture_creator
+            .create_texture_static(
+                PixelFormatEnum::ARGB8888,
+                cell_size.0 * ATLAS_CELLS.0,
+                cell_size.1 * ATLAS_CELLS.1,
+            )
+            .map_err(|e| e.to_string())?;
+        texture.set_blend_mode(BlendMode::Blend);
+        Ok(Self {
+            font,
+            texture,
+            cell_size,
+            cells: HashMap::new(),
+            next: 0,
+        })
+    }
+
+    pub fn texture(&self) -> &Texture<'r> {
+        &self.texture
+    }
+
+    pub fn texture_mut(&mut self) -> &mut Texture<'r> {
+        &mut self.texture
+    }
+
+    /// Returns the atlas area holding `c`, rasterising it on first use, or `None` if the
+    /// font has no glyph for it.
+    pub fn srcrect(&mut self, c: char) -> Result<Option<Rect>, String> {
+        if let Some(rect) = self.cells.get(&c) {
+            return Ok(*rect);
+        }
+        let rect = match self.font.find_glyph(c) {
+            Some(_) => Some(self.rasterize(c)?),
+            None => None,
+        };
+        self.cells.insert(c, rect);
+        Ok(rect)
+    }
+
+    /// True if looking up `c` would start the atlas over, so that rects returned before
+    /// it no longer hold their glyphs.
+    pub fn evicts(&self, c: char) -> bool {
+        !self.cells.contains_key(&c) && self.next == ATLAS_CELLS.0 * ATLAS_CELLS.1
+    }
+
+    fn rasterize(&mut self, c: char) -> Result<Rect, String> {
+        // When the atlas fills up start over, anything already drawn to the frame stays put
+        if self.next == ATLAS_CELLS.0 * ATLAS_CELLS.1 {
+            self.cells.clear();
+            self.next = 0;
+        }
+        let (w, h) = self.cell_size;
+        let rect = Rect::new(
+            ((self.next % ATLAS_CELLS.0) * w) as i32,
+            ((self.next / ATLAS_CELLS.0) * h) as i32,
+            w,
+            h,
+        );
+        self.next += 1;
+
+        let mut rendered = self
+            .font
+            .render_char(c)
+            .blended(Color::RGBA(255, 255, 255, 255))
+            .map_err(|e| e.to_string())?;
+        rendered.set_blend_mode(BlendMode::None)?;
+
+        // Shrink glyphs that are too wide for a cell and centre them
+        let (rw, rh) = (rendered.width().max(1), rendered.height());
+        let (gw, gh) = if rw > w { (w, rh * w / rw) } else { (rw, rh) };
+        let gh = gh.min(h);
+        let dstrect = Rect::new(((w - gw) / 2) as i32, ((h - gh) / 2) as i32, gw, gh);
+        let mut cell = Surface::new(w, h, PixelFormatEnum::ARGB8888)?;
+        rendered.blit_scaled(None, &mut cell, dstrect)?;
+
+        let pitch = cell.pitch() as usize;
+        let texture = &mut self.texture;
+        cell.with_lock(|pixels| texture.update(rect, pixels, pitch))
+            .map_err(|e| e.to_string())?;
+
+        Ok(rect)
+    }
+}
@@ -0,0 +1,145 @@
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::fs::File;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::cp437::Cp437;
+
+// Set on ids holding a Unicode scalar value to be drawn from the TrueType font
+const CHAR_FLAG: u32 = 1 << 31;
+
+/// Index of a cell in a tileset, counted left to right and top to bottom.
+///
+/// The first 256 ids are the `Cp437` glyphs, anything past that addresses extra
+/// sprite rows in larger sheets. Glyphs made with `from_char` are rendered from the
+/// font atlas instead.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(from = "GlyphRepr", into = "GlyphRepr")]
+pub struct Glyph(pub u32);
+
+// How glyphs are written out, so files don't depend on the id encoding
+#[derive(Serialize, Deserialize)]
+enum GlyphRepr {
+    Cp437(Cp437),
+    Sheet(u32),
+    Char(#[serde(with = "crate::cp437::char_code")] char),
+}
+
+impl From<GlyphRepr> for Glyph {
+    fn from(repr: GlyphRepr) -> Self {
+        match repr {
+            GlyphRepr::Cp437(cp) => cp.into(),
+            GlyphRepr::Sheet(id) => Glyph(id),
+            GlyphRepr::Char(c) => Glyph::from_char(c),
+        }
+    }
+}
+
+impl From<Glyph> for GlyphRepr {
+    fn from(glyph: Glyph) -> Self {
+        match (glyph.to_char(), glyph.to_cp437()) {
+            (Some(c), _) => GlyphRepr::Char(c),
+            (None, Some(cp)) => GlyphRepr::Cp437(cp),
+            (None, None) => GlyphRepr::Sheet(glyph.id()),
+        }
+    }
+}
+
+impl Glyph {
+    pub fn from_char(c: char) -> Self {
+        Glyph(CHAR_FLAG | c as u32)
+    }
+
+    /// True for glyphs that draw nothing but their background.
+    pub fn is_blank(self) -> bool {
+        match self.to_cp437() {
+            Some(Cp437::Null) | Some(Cp437::Space) | Some(Cp437::NoBreakSpace) => true,
+            _ => self.to_char().is_some_and(char::is_whitespace),
+        }
+    }
+
+    /// The tileset glyph used to draw this glyph without a font.
+    pub fn sheet_glyph(self) -> Option<Glyph> {
+        match self.to_char() {
+            Some(c) => Cp437::try_from(c).ok().map(Glyph::from),
+            None => Some(self),
+        }
+    }
+
+    /// The character to show for this glyph in text, with spaces for control
+    /// characters and `?` for sheet glyphs past the CP437 range.
+    pub fn display_char(self) -> char {
+        match self
+            .to_char()
+            .or_else(|| self.to_cp437().map(|cp| cp.to_char()))
+        {
+            Some(c) if c.is_control() => ' ',
+            Some(c) => c,
+            None => '?',
+        }
+    }
+
+    pub fn to_char(self) -> Option<char> {
+        if self.0 & CHAR_FLAG == 0 {
+            return None;
+        }
+        std::char::from_u32(self.0 & !CHAR_FLAG)
+    }
+
+    pub fn id(self) -> u32 {
+        self.0
+    }
+
+    pub fn to_cp437(self) -> Option<Cp437> {
+        Cp437::try_from(self.0).ok()
+    }
+}
+
+impl From<Cp437> for Glyph {
+    fn from(cp: Cp437) -> Self {
+        Glyph(cp as u32)
+    }
+}
+
+/// Uses the CP437 glyph where there is one, and the font otherwise.
+impl From<char> for Glyph {
+    fn from(c: char) -> Self {
+        match Cp437::try_from(c) {
+            Ok(cp) => cp.into(),
+            Err(_) => Glyph::from_char(c),
+        }
+    }
+}
+
+/// Maps glyph names to `(column, row)` cells of a tileset.
+#[derive(Debug, Default)]
+pub struct GlyphMap {
+    cells: HashMap<String, (u32, u32)>,
+}
+
+impl GlyphMap {
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let file = File::open(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        let cells = ron::de::from_reader(file).map_err(|e| format!("{}: {}", path.display(), e))?;
+        Ok(Self { cells })
+    }
+
+    /// Turns each cell into a glyph id for a sheet `columns` wide and `rows` tall.
+    pub fn resolve(&self, columns: u32, rows: u32) -> Result<HashMap<String, Glyph>, String> {
+        self.cells
+            .iter()
+            .map(|(name, &(x, y))| {
+                if x >= columns || y >= rows {
+                    Err(format!(
+                        "glyph {:?} at ({}, {}) is outside the {}x{} tileset",
+                        name, x, y, columns, rows
+                    ))
+                } else {
+                    Ok((name.clone(), Glyph(y * columns + x)))
+                }
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,272 @@
+use std::collections::{HashMap, VecDeque};
+use std::path::Path;
+
+use sdl2::image::SaveSurface;
+use sdl2::pixels::{Color, PixelFormatEnum};
+use sdl2::render::BlendMode;
+use sdl2::surface::Surface;
+
+use crate::backend::{Backend, Input};
+use crate::config::Config;
+use crate::console::{Console, Tile};
+use crate::glyph::Glyph;
+use crate::tileset::{SheetLayout, TilesetDesc};
+
+/// Tightly packed 8-bit RGBA pixels.
+#[derive(Debug, Clone)]
+pub struct RgbaImage {
+    width: u32,
+    height: u32,
+    pixels: Vec<u8>,
+}
+
+impl RgbaImage {
+    pub fn new(width: u32, height: u32) -> Self {
+        Self {
+            width,
+            height,
+            pixels: vec![0; (width * height * 4) as usize],
+        }
+    }
+
+    /// Wraps `pixels`, which must hold `width * height` RGBA pixels.
+    pub fn from_pixels(width: u32, height: u32, pixels: Vec<u8>) -> Self {
+        assert_eq!(pixels.len(), (width * height * 4) as usize);
+        Self {
+            width,
+            height,
+            pixels,
+        }
+    }
+
+    /// Copies `surface` as is, leaving colour keyed pixels transparent.
+    pub fn from_surface(mut surface: Surface) -> Result<Self, String> {
+        let (width, height) = surface.size();
+        let mut rgba = Surface::new(width, height, PixelFormatEnum::RGBA32)?;
+        surface.set_blend_mode(BlendMode::None)?;
+        surface.blit(None, &mut rgba, None)?;
+
+        let pitch = rgba.pitch() as usize;
+        let row = width as usize * 4;
+        let pixels = rgba.with_lock(|p| {
+            (0..height as usize)
+                .flat_map(|y| p[y * pitch..y * pitch + row].iter().copied())
+                .collect()
+        });
+        Ok(Self {
+            width,
+            height,
+            pixels,
+        })
+    }
+
+    pub fn save_png(&self, path: &Path) -> Result<(), String> {
+        let mut pixels = self.pixels.clone();
+        let surface = Surface::from_data(
+            &mut pixels,
+            self.width,
+            self.height,
+            self.width * 4,
+            PixelFormatEnum::RGBA32,
+        )?;
+        surface.save(path)
+    }
+}
+
+/// Rasterises consoles on the CPU, giving the same pixels as drawing to the frame
+/// texture through SDL. Font glyphs fall back on the sheet like they do without a font.
+pub struct SoftwareRenderer {
+    layout: SheetLayout,
+    sheet: RgbaImage,
+    names: HashMap<String, Glyph>,
+}
+
+impl SoftwareRenderer {
+    pub fn new(desc: &TilesetDesc) -> Result<Self, String> {
+        let surface = desc.load_surface()?;
+        let layout = SheetLayout::new(desc, surface.size())?;
+        Ok(Self {
+            names: layout.glyph_names(desc)?,
+            layout,
+            sheet: RgbaImage::from_surface(surface)?,
+        })
+    }
+
+    pub fn tile_size(&self) -> (u32, u32) {
+        self.layout.tile_size()
+    }
+
+    /// Looks up a glyph by the name given to it in the glyph map.
+    pub fn glyph(&self, name: &str) -> Option<Glyph> {
+        self.names.get(name).copied()
+    }
+
+    pub fn render(&self, console: &Console) -> RgbaImage {
+        let (tile_w, tile_h) = self.tile_size();
+        let mut image = RgbaImage::new(console.width() * tile_w, console.height() * tile_h);
+        self.draw_tiles(&mut image, console.tiles().iter());
+        image
+    }
+
+    /// Redraws `tiles`, e.g. `Console::dirty_tiles`, onto an image made by `render`.
+    pub fn draw_tiles<'a>(&self, image: &mut RgbaImage, tiles: impl Iterator<Item = &'a Tile>) {
+        for tile in tiles {
+            self.draw_tile(image, tile);
+        }
+    }
+
+    // Fill with the background, then alpha blend the glyph tinted by the foreground,
+    // like a filled rect and a colour modded texture copy
+    fn draw_tile(&self, image: &mut RgbaImage, tile: &Tile) {
+        let (tile_w, tile_h) = self.tile_size();
+        let (x0, y0) = (tile.row * tile_w, tile.col * tile_h);
+        if x0 + tile_w > image.width || y0 + tile_h > image.height {
+            return;
+        }
+        let srcrect = tile
+            .code_point
+            .sheet_glyph()
+            .and_then(|glyph| self.layout.srcrect(glyph));
+        let Color { r, g, b, a } = tile.background;
+        let fg = tile.foreground;
+        let tint = [fg.r as u32, fg.g as u32, fg.b as u32];
+
+        for y in 0..tile_h {
+            for x in 0..tile_w {
+                let mut px = [r as u32, g as u32, b as u32, a as u32];
+                if let Some(srcrect) = srcrect {
+                    let sx = srcrect.x() as u32 + x;
+                    let sy = srcrect.y() as u32 + y;
+                    let si = ((sy * self.sheet.width + sx) * 4) as usize;
+                    let src = &self.sheet.pixels[si..si + 4];
+                    let src_a = src[3] as u32;
+                    for c in 0..3 {
+                        let modded = src[c] as u32 * tint[c] / 255;
+                        px[c] = (modded * src_a + px[c] * (255 - src_a)) / 255;
+                    }
+                    px[3] = src_a + px[3] * (255 - src_a) / 255;
+                }
+                let di = (((y0 + y) * image.width + x0 + x) * 4) as usize;
+                for (dst, &value) in image.pixels[di..di + 4].iter_mut().zip(&px) {
+                    *dst = value as u8;
+                }
+            }
+        }
+    }
+}
+
+/// Renders frames to an offscreen image without a display, for automated runs. Input
+/// comes from `push_input` and the backend quits after a set number of frames.
+pub struct HeadlessBackend {
+    renderer: SoftwareRenderer,
+    frame: Option<RgbaImage>,
+    input: VecDeque<Input>,
+    frames_left: u32,
+}
+
+impl HeadlessBackend {
+    pub fn new(desc: &TilesetDesc, frames: u32) -> Result<Self, String> {
+        Ok(Self {
+            renderer: SoftwareRenderer::new(desc)?,
+            frame: None,
+            input: VecDeque::new(),
+            frames_left: frames,
+        })
+    }
+
+    /// Queues input to be returned by a later `poll_input`, one frame each.
+    pub fn push_input(&mut self, input: Input) {
+        self.input.push_back(input);
+    }
+
+    /// The last frame presented.
+    pub fn frame(&self) -> Option<&RgbaImage> {
+        self.frame.as_ref()
+    }
+}
+
+impl Backend for HeadlessBackend {
+    fn poll_input(&mut self) -> Result<Input, String> {
+        if self.frames_left == 0 {
+            return Ok(Input {
+                quit: true,
+                ..Input::default()
+            });
+        }
+        self.frames_left -= 1;
+        Ok(self.input.pop_front().unwrap_or_default())
+    }
+
+    fn present(&mut self, console: &Console) -> Result<(), String> {
+        match &mut self.frame {
+            Some(frame) => self.renderer.draw_tiles(frame, console.dirty_tiles()),
+            None => self.frame = Some(self.renderer.render(console)),
+        }
+        Ok(())
+    }
+
+    fn resize(&mut self, _size: (u32, u32)) -> Result<(), String> {
+        self.frame = None;
+        Ok(())
+    }
+
+    fn configure(&mut self, _config: &Config) -> Result<(), String> {
+        Ok(())
+    }
+
+    fn fitting_size(&self) -> Option<(u32, u32)> {
+        None
+    }
+
+    fn glyph(&self, name: &str) -> Option<Glyph> {
+        self.renderer.glyph(name)
+    }
+
+    fn screenshot(&mut self) -> Result<Option<RgbaImage>, String> {
+        Ok(self.frame.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cp437::Cp437;
+
+    fn tile_pixels(image: &RgbaImage, (tile_w, tile_h): (u32, u32), x: u32) -> Vec<[u8; 4]> {
+        let mut pixels = Vec::new();
+        for py in 0..tile_h {
+            for px in x * tile_w..(x + 1) * tile_w {
+                let i = ((py * image.width + px) * 4) as usize;
+                let p = &image.pixels[i..i + 4];
+                pixels.push([p[0], p[1], p[2], p[3]]);
+            }
+        }
+        pixels
+    }
+
+    #[test]
+    fn renders_backgrounds_and_tinted_glyphs() {
+        let desc = TilesetDesc::load(Path::new("res/tileset.ron")).unwrap();
+        let renderer = SoftwareRenderer::new(&desc).unwrap();
+        let tile_size = renderer.tile_size();
+
+        let black = Color::RGBA(0, 0, 0, 255);
+        let green = Color::RGBA(0, 255, 0, 255);
+        let red = Color::RGBA(200, 0, 0, 255);
+        let mut console = Console::new(2, 1);
+        // Past the end of the sheet, so only the background is drawn
+        console.put(0, 0, Glyph(100_000), green, red);
+        console.put(1, 0, Cp437::LatinCapitalLetterA.into(), green, black);
+        let image = renderer.render(&console);
+        assert_eq!((image.width, image.height), (2 * tile_size.0, tile_size.1));
+
+        let blank = tile_pixels(&image, tile_size, 0);
+        assert!(blank.iter().all(|&p| p == [200, 0, 0, 255]));
+
+        // The glyph's ink takes the foreground and the rest shows the background
+        let letter = tile_pixels(&image, tile_size, 1);
+        assert!(letter.contains(&[0, 0, 0, 255]));
+        assert!(letter.iter().any(|p| p[1] > 128 && p[0] == 0 && p[2] == 0));
+        assert!(letter.iter().all(|p| p[0] == 0 && p[2] == 0 && p[3] == 255));
+    }
+}
@@ -0,0 +1,346 @@
+use std::collections::HashMap;
+use std::f32::consts::PI;
+use std::fs::File;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use sdl2::pixels::Color;
+
+use crate::color;
+use crate::glyph::Glyph;
+
+/// How a value moves from one keyframe to the next.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Easing {
+    #[default]
+    Linear,
+    EaseIn,
+    EaseOut,
+    EaseInOut,
+    /// Holds the value until the next keyframe.
+    Step,
+    Sine,
+}
+
+impl Easing {
+    /// Maps progress between two keyframes, from 0 to 1, onto how far the value has
+    /// moved between them.
+    pub fn apply(self, t: f32) -> f32 {
+        let t = t.clamp(0.0, 1.0);
+        match self {
+            Easing::Linear => t,
+            Easing::EaseIn => t * t,
+            Easing::EaseOut => t * (2.0 - t),
+            Easing::EaseInOut if t < 0.5 => 2.0 * t * t,
+            Easing::EaseInOut => -1.0 + (4.0 - 2.0 * t) * t,
+            Easing::Step => 0.0,
+            Easing::Sine => 0.5 - 0.5 * (PI * t).cos(),
+        }
+    }
+}
+
+/// What happens after the last keyframe.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Repeat {
+    /// Holds the last keyframe.
+    Once,
+    #[default]
+    Loop,
+    /// Plays backwards to the start, then forwards again.
+    PingPong,
+}
+
+/// Per-tile time offsets, so neighbouring tiles don't change in lockstep.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Phase {
+    /// Every tile plays in time.
+    #[default]
+    Together,
+    /// A random offset of up to this many seconds, fixed for each cell.
+    Random(f32),
+    /// Seconds of offset per cell across and down, for waves.
+    Gradient(f32, f32),
+}
+
+impl Phase {
+    pub fn offset(self, x: u32, y: u32) -> f32 {
+        match self {
+            Phase::Together => 0.0,
+            Phase::Random(max) => cell_noise(x, y) * max,
+            Phase::Gradient(dx, dy) => x as f32 * dx + y as f32 * dy,
+        }
+    }
+}
+
+// A fixed value in 0..1 for each cell
+fn cell_noise(x: u32, y: u32) -> f32 {
+    let mut h = x.wrapping_mul(0x9e37_79b9) ^ y.wrapping_mul(0x85eb_ca6b);
+    h ^= h >> 16;
+    h = h.wrapping_mul(0x7feb_352d);
+    h ^= h >> 15;
+    (h >> 8) as f32 / (1 << 24) as f32
+}
+
+/// Values a tile takes at `time` seconds into an animation. Anything left out keeps
+/// following the keyframes either side, or the tile itself if no keyframe sets it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Keyframe {
+    pub time: f32,
+    #[serde(default)]
+    pub glyph: Option<Glyph>,
+    #[serde(default)]
+    pub fg: Option<(u8, u8, u8, u8)>,
+    #[serde(default)]
+    pub bg: Option<(u8, u8, u8, u8)>,
+    /// Easing towards the next keyframe.
+    #[serde(default)]
+    pub easing: Easing,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct KeyframeAnimation {
+    /// Sorted by time, the last one sets the length of the animation.
+    pub keyframes: Vec<Keyframe>,
+    #[serde(default)]
+    pub repeat: Repeat,
+    #[serde(default)]
+    pub phase: Phase,
+}
+
+impl KeyframeAnimation {
+    pub fn duration(&self) -> f32 {
+        self.keyframes.last().map_or(0.0, |k| k.time)
+    }
+
+    /// The tile at `(x, y)`, drawn as `tile`, `time` seconds after the animation
+    /// started.
+    pub fn sample(
+        &self,
+        time: f32,
+        (x, y): (u32, u32),
+        (glyph, fg, bg): (Glyph, Color, Color),
+    ) -> (Glyph, Color, Color) {
+        let time = self.local_time(time + self.phase.offset(x, y));
+        let rgba = |(r, g, b, a)| Color::RGBA(r, g, b, a);
+        let glyph = self.track(time, |k| k.glyph, |a, _, _| a).unwrap_or(glyph);
+        let fg = self
+            .track(time, |k| k.fg.map(rgba), color::lerp)
+            .unwrap_or(fg);
+        let bg = self
+            .track(time, |k| k.bg.map(rgba), color::lerp)
+            .unwrap_or(bg);
+        (glyph, fg, bg)
+    }
+
+    // Folds time into the keyframes' range according to the repeat mode
+    fn local_time(&self, time: f32) -> f32 {
+        let duration = self.duration();
+        if duration <= 0.0 {
+            return 0.0;
+        }
+        match self.repeat {
+            Repeat::Once => time.clamp(0.0, duration),
+            Repeat::Loop => time.rem_euclid(duration),
+            Repeat::PingPong => {
+                let t = time.rem_euclid(2.0 * duration);
+                if t > duration {
+                    2.0 * duration - t
+                } else {
+                    t
+                }
+            }
+        }
+    }
+
+    // Interpolates the keyframes that set one value
+    fn track<T: Copy>(
+        &self,
+        time: f32,
+        value: impl Fn(&Keyframe) -> Option<T>,
+        mix: impl Fn(T, T, f32) -> T,
+    ) -> Option<T> {
+        let mut before: Option<(&Keyframe, T)> = None;
+        for keyframe in &self.keyframes {
+            let v = match value(keyframe) {
+                Some(v) => v,
+                None => continue,
+            };
+            if keyframe.time > time {
+                return Some(match before {
+                    Some((prev, pv)) => {
+                        let span = keyframe.time - prev.time;
+                        let t = if span > 0.0 {
+                            (time - prev.time) / span
+                        } else {
+                            1.0
+                        };
+                        mix(pv, v, prev.easing.apply(t))
+                    }
+                    None => v,
+                });
+            }
+            before = Some((keyframe, v));
+        }
+        before.map(|(_, v)| v)
+    }
+}
+
+/// Keyframe animations by name, loaded from a RON map.
+#[derive(Debug, Default)]
+pub struct AnimationLibrary {
+    animations: HashMap<String, KeyframeAnimation>,
+}
+
+impl AnimationLibrary {
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let file = File::open(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        let mut animations: HashMap<String, KeyframeAnimation> =
+            ron::de::from_reader(file).map_err(|e| format!("{}: {}", path.display(), e))?;
+        for (name, animation) in animations.iter_mut() {
+            if let Some(keyframe) = animation
+                .keyframes
+                .iter()
+                .find(|k| !k.time.is_finite() || k.time < 0.0)
+            {
+                return Err(format!(
+                    "{}: {} has a keyframe at {} seconds",
+                    path.display(),
+                    name,
+                    keyframe.time
+                ));
+            }
+            animation
+                .keyframes
+                .sort_by(|a, b| a.time.total_cmp(&b.time));
+        }
+        Ok(Self { animations })
+    }
+
+    pub fn get(&self, name: &str) -> Option<&KeyframeAnimation> {
+        self.animations.get(name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cp437::Cp437;
+
+    fn keyframe(time: f32, glyph: Cp437, fg: (u8, u8, u8, u8)) -> Keyframe {
+        Keyframe {
+            time,
+            glyph: Some(glyph.into()),
+            fg: Some(fg),
+            bg: None,
+            easing: Easing::Linear,
+        }
+    }
+
+    fn animation(repeat: Repeat) -> KeyframeAnimation {
+        KeyframeAnimation {
+            keyframes: vec![
+                keyframe(0.0, Cp437::LatinCapitalLetterA, (0, 0, 0, 255)),
+                keyframe(2.0, Cp437::LatinCapitalLetterB, (200, 100, 0, 255)),
+            ],
+            repeat,
+            phase: Phase::Together,
+        }
+    }
+
+    #[test]
+    fn easings_start_and_end_on_the_keyframes() {
+        for easing in [
+            Easing::Linear,
+            Easing::EaseIn,
+            Easing::EaseOut,
+            Easing::EaseInOut,
+            Easing::Sine,
+        ] {
+            assert!(easing.apply(0.0).abs() < 1e-6, "{:?}", easing);
+            assert!((easing.apply(1.0) - 1.0).abs() < 1e-6, "{:?}", easing);
+            // Progress outside 0..1 is clamped
+            assert_eq!(easing.apply(-1.0), easing.apply(0.0));
+            assert_eq!(easing.apply(2.0), easing.apply(1.0));
+        }
+        assert_eq!(Easing::Step.apply(0.0), 0.0);
+        assert_eq!(Easing::Step.apply(1.0), 0.0);
+    }
+
+    #[test]
+    fn folds_time_by_repeat_mode() {
+        let once = animation(Repeat::Once);
+        assert_eq!(once.local_time(-1.0), 0.0);
+        assert_eq!(once.local_time(1.5), 1.5);
+        assert_eq!(once.local_time(5.0), 2.0);
+
+        let looped = animation(Repeat::Loop);
+        assert_eq!(looped.local_time(3.0), 1.0);
+        assert_eq!(looped.local_time(-0.5), 1.5);
+
+        let ping_pong = animation(Repeat::PingPong);
+        assert_eq!(ping_pong.local_time(1.0), 1.0);
+        assert_eq!(ping_pong.local_time(3.0), 1.0);
+        assert_eq!(ping_pong.local_time(4.5), 0.5);
+    }
+
+    #[test]
+    fn samples_between_and_outside_keyframes() {
+        let once = animation(Repeat::Once);
+        let tile = (
+            Cp437::Space.into(),
+            Color::RGBA(1, 2, 3, 255),
+            Color::RGBA(4, 5, 6, 255),
+        );
+        // Glyphs hold until the next keyframe, colours are eased, and values no
+        // keyframe sets are left as they are
+        assert_eq!(
+            once.sample(1.0, (0, 0), tile),
+            (
+                Cp437::LatinCapitalLetterA.into(),
+                Color::RGBA(100, 50, 0, 255),
+                tile.2
+            )
+        );
+        assert_eq!(
+            once.sample(-1.0, (0, 0), tile),
+            (
+                Cp437::LatinCapitalLetterA.into(),
+                Color::RGBA(0, 0, 0, 255),
+                tile.2
+            )
+        );
+        assert_eq!(
+            once.sample(10.0, (0, 0), tile),
+            (
+                Cp437::LatinCapitalLetterB.into(),
+                Color::RGBA(200, 100, 0, 255),
+                tile.2
+            )
+        );
+    }
+
+    #[test]
+    fn rejects_keyframe_times_that_cant_be_sorted() {
+        for (time, message) in [("-1.0", "at -1 seconds"), ("1e99", "at inf seconds")] {
+            let path = std::env::temp_dir().join(format!(
+                "keyframes-test-{}-{}.ron",
+                std::process::id(),
+                time
+            ));
+            let text = format!("{{\"bad\": (keyframes: [(time: 1.0), (time: {})])}}", time);
+            std::fs::write(&path, text).unwrap();
+            let result = AnimationLibrary::load(&path);
+            std::fs::remove_file(&path).unwrap();
+            let error = result.unwrap_err();
+            assert!(error.contains(message), "{}", error);
+        }
+    }
+
+    #[test]
+    fn loads_the_game_animations() {
+        let library = AnimationLibrary::load(Path::new("res/animations.ron")).unwrap();
+        let lava = library.get("lava").unwrap();
+        assert!(lava.keyframes.windows(2).all(|k| k[0].time <= k[1].time));
+    }
+}
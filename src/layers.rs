@@ -0,0 +1,216 @@
+use std::collections::HashMap;
+
+use sdl2::pixels::Color;
+
+use crate::console::{blend, Animation, Console};
+use crate::cp437::Cp437;
+use crate::glyph::Glyph;
+
+pub struct Layer {
+    pub name: String,
+    pub console: Console,
+    /// Position of the layer's top left tile on the composited console.
+    pub offset: (i32, i32),
+    pub visible: bool,
+    /// Multiplies the alpha of every tile on the layer.
+    pub opacity: f32,
+}
+
+impl Layer {
+    pub fn new(name: &str, console: Console) -> Self {
+        Self {
+            name: name.to_string(),
+            console,
+            offset: (0, 0),
+            visible: true,
+            opacity: 1.0,
+        }
+    }
+}
+
+/// Console layers drawn bottom to top in the order they were pushed.
+///
+/// Tile alpha controls how much of the layers below shows through, see `console::blend`.
+#[derive(Default)]
+pub struct Layers {
+    layers: Vec<Layer>,
+    scratch: Vec<(Glyph, Color, Color)>,
+    // Offset, visibility and opacity of each layer at the last compose
+    composed: Vec<((i32, i32), bool, f32)>,
+    // Animations of the topmost glyph in each uncovered cell that has any
+    animated: Vec<(usize, Vec<Animation>)>,
+}
+
+impl Layers {
+    pub fn push(&mut self, layer: Layer) {
+        self.layers.push(layer);
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Layer> {
+        self.layers.iter().find(|l| l.name == name)
+    }
+
+    pub fn get_mut(&mut self, name: &str) -> Option<&mut Layer> {
+        self.layers.iter_mut().find(|l| l.name == name)
+    }
+
+    #[allow(dead_code)]
+    pub fn iter(&self) -> impl Iterator<Item = &Layer> {
+        self.layers.iter()
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut Layer> {
+        self.layers.iter_mut()
+    }
+
+    /// The result of the last `compose` before animation, in row-major order.
+    pub fn flattened(&self) -> &[(Glyph, Color, Color)] {
+        &self.scratch
+    }
+
+    /// Composited cells with animations, by index into `flattened`. Cells under an
+    /// opaque tile on a higher layer aren't animated.
+    pub fn animated(&self) -> &[(usize, Vec<Animation>)] {
+        &self.animated
+    }
+
+    /// Makes the next `compose` redraw the console even if no layer changed, for when
+    /// something else has drawn over it.
+    pub fn invalidate(&mut self) {
+        self.composed.clear();
+    }
+
+    /// Resizes every layer, filling new tiles with transparent spaces.
+    pub fn resize(&mut self, size: (u32, u32)) {
+        let transparent = Color::RGBA(0, 0, 0, 0);
+        for layer in self.layers.iter_mut() {
+            layer
+                .console
+                .resize(size, Cp437::Space.into(), transparent, transparent);
+        }
+    }
+
+    /// Flattens the visible layers onto `target`, marking tiles dirty only where the
+    /// result changed. Does nothing if no layer changed since the last call.
+    pub fn compose(&mut self, target: &mut Console) {
+        let (width, height) = (target.width() as i32, target.height() as i32);
+        let composed: Vec<_> = self
+            .layers
+            .iter()
+            .map(|l| (l.offset, l.visible, l.opacity))
+            .collect();
+        if composed == self.composed
+            && self.scratch.len() == (width * height) as usize
+            && !self.layers.iter().any(|l| l.console.is_dirty())
+        {
+            return;
+        }
+        self.composed = composed;
+        let empty = (
+            Cp437::Null.into(),
+            Color::RGBA(0, 0, 0, 255),
+            Color::RGBA(0, 0, 0, 255),
+        );
+        self.scratch.clear();
+        self.scratch.resize((width * height) as usize, empty);
+        // Animations with the layer they're on, and the topmost layer with an opaque tile
+        // in each cell, which hides anything animated below it
+        let mut animated = HashMap::new();
+        let mut covered = vec![None; self.scratch.len()];
+
+        for (depth, layer) in self.layers.iter_mut().enumerate() {
+            // The whole stack is recomposited, so layers don't need their own dirty state
+            layer.console.reset_tiles();
+            if !layer.visible || layer.opacity <= 0.0 {
+                continue;
+            }
+            let (ox, oy) = layer.offset;
+            for tile in layer.console.tiles() {
+                let (x, y) = (tile.row as i32 + ox, tile.col as i32 + oy);
+                if x < 0 || y < 0 || x >= width || y >= height {
+                    continue;
+                }
+                let index = (x + y * width) as usize;
+                let cell = &mut self.scratch[index];
+                *cell = blend(*cell, tile, layer.opacity, layer.opacity);
+                // A glyph drawn over another takes its animations with it
+                if cell.0 == tile.code_point && !tile.code_point.is_blank() {
+                    if tile.animations.is_empty() {
+                        animated.remove(&index);
+                    } else {
+                        animated.insert(index, (depth, tile.animations.clone()));
+                    }
+                }
+                if tile.background.a == 255 && layer.opacity >= 1.0 {
+                    covered[index] = Some(depth);
+                }
+            }
+        }
+        let (columns, rows) = (width as usize, height as usize);
+        self.animated = animated
+            .into_iter()
+            .filter_map(|(index, (depth, mut animations))| {
+                let hidden = |i: usize| covered[i].is_some_and(|top| top > depth);
+                if hidden(index) {
+                    return None;
+                }
+                // Shifted glyphs can't land on cells covered from above either
+                animations.retain(|animation| match animation {
+                    Animation::HorizontalShift => {
+                        index % columns + 1 < columns && !hidden(index + 1)
+                    }
+                    Animation::VerticalShift => {
+                        index / columns + 1 < rows && !hidden(index + columns)
+                    }
+                    _ => true,
+                });
+                Some((index, animations)).filter(|(_, animations)| !animations.is_empty())
+            })
+            .collect();
+        self.animated.sort_by_key(|&(index, _)| index);
+
+        for (i, &(glyph, fg, bg)) in self.scratch.iter().enumerate() {
+            let tile = &target.tiles()[i];
+            if tile.code_point != glyph || tile.foreground != fg || tile.background != bg {
+                let (x, y) = (i as i32 % width, i as i32 / width);
+                target.put(x, y, glyph, fg, bg);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn covered_cells_arent_animated() {
+        let black = Color::RGBA(0, 0, 0, 255);
+        let transparent = Color::RGBA(0, 0, 0, 0);
+        let shift = Color::RGBA(255, 255, 255, 255);
+        let mut map = Console::new(3, 1);
+        for x in 0..3 {
+            map.put(x, 0, Cp437::LatinSmallLetterX.into(), black, black);
+            map.tile_mut(x as u32, 0).unwrap().animations = vec![
+                Animation::ColorShift(1.0, black, shift),
+                Animation::HorizontalShift,
+            ];
+        }
+        // A blank panel over the middle cell
+        let mut ui = Console::new(3, 1);
+        ui.clear(Cp437::Space.into(), transparent, transparent);
+        ui.put(1, 0, Cp437::Space.into(), black, black);
+
+        let mut layers = Layers::default();
+        layers.push(Layer::new("map", map));
+        layers.push(Layer::new("ui", ui));
+        layers.compose(&mut Console::new(3, 1));
+
+        // The first cell can't shift under the panel, the last has nowhere to shift
+        let colour_shift = vec![Animation::ColorShift(1.0, black, shift)];
+        assert_eq!(
+            layers.animated(),
+            &[(0, colour_shift.clone()), (2, colour_shift)][..]
+        );
+    }
+}
@@ -1,22 +1,17 @@
 use std::collections::HashSet;
-use std::path::Path;
-use std::time::{Duration, Instant};
+use std::env;
+use std::f32::consts::FRAC_PI_2;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Instant;
 
 use rand::prelude::*;
 
 use specs::prelude::*;
 use specs_derive::{Component, ConvertSaveload};
 
-use sdl2::event::{Event, WindowEvent};
-use sdl2::image::{InitFlag, LoadSurface};
 use sdl2::keyboard::Keycode;
-use sdl2::pixels::{Color, PixelFormatEnum};
-use sdl2::rect::Rect;
-use sdl2::render::{Canvas, Texture};
-use sdl2::surface::Surface;
-use sdl2::video::Window;
-
-use fps_counter::FPSCounter;
+use sdl2::pixels::Color;
 
 #[derive(Debug, Component)]
 #[storage(VecStorage)]
@@ -26,372 +21,382 @@ struct Vel(f32);
 #[storage(VecStorage)]
 struct Pos(f32);
 
-mod cp437;
-use cp437::{Coords, Cp437};
+mod animation;
+use animation::{AnimationSys, FrameTime};
 
-const TILE_SIZE: (u32, u32) = (14, 16);
-const CONSOLE_SIZE: (u32, u32) = (140, 60);
-const WINDOW_SIZE: (u32, u32) = (1280, 720);
-
-fn update_dstrect(dstrect: &mut Rect, (w, h): (u32, u32)) {
-    let rat_w: f32 = w as f32 / WINDOW_SIZE.0 as f32;
-    let rat_h: f32 = h as f32 / WINDOW_SIZE.1 as f32;
-    if rat_w > rat_h {
-        dstrect.w = (rat_h * WINDOW_SIZE.0 as f32) as i32;
-        dstrect.h = h as i32;
-        dstrect.x = ((w as i32 - dstrect.w) as f32 / 2f32) as i32;
-        dstrect.y = 0;
-    } else {
-        dstrect.w = w as i32;
-        dstrect.h = (rat_w * WINDOW_SIZE.1 as f32) as i32;
-        dstrect.x = 0;
-        dstrect.y = ((h as i32 - dstrect.h) as f32 / 2f32) as i32;
-    }
-}
+mod ans;
 
-fn randomize_tiles(
-    canvas: &mut Canvas<Window>,
-    frame_texture: &mut Texture,
-    tiles_texture: &mut Texture,
-) -> Result<(), String> {
-    canvas
-        .with_texture_canvas(frame_texture, |texture_canvas| {
-            for x in 0..CONSOLE_SIZE.0 {
-                for y in 0..CONSOLE_SIZE.1 {
-                    let coords = Coords::from(Cp437::from(random::<u32>() % (Cp437::Count as u32)));
-                    let srcrect = Rect::new(
-                        (TILE_SIZE.0 as i32) * coords.row,
-                        (TILE_SIZE.1 as i32) * coords.col,
-                        TILE_SIZE.0,
-                        TILE_SIZE.1,
-                    );
-                    let dstrect = Rect::new(
-                        (x * TILE_SIZE.0) as i32,
-                        (y * TILE_SIZE.1) as i32,
-                        TILE_SIZE.0,
-                        TILE_SIZE.1,
-                    );
-                    tiles_texture.set_color_mod(random::<u8>(), random::<u8>(), random::<u8>());
-                    texture_canvas.set_draw_color(Color::RGBA(
-                        random::<u8>() % 32u8,
-                        random::<u8>() % 32u8,
-                        random::<u8>() % 32u8,
-                        255,
-                    ));
-                    texture_canvas
-                        .fill_rect(Some(dstrect))
-                        .expect("failed to draw rect");
-                    texture_canvas
-                        .copy(&tiles_texture, srcrect, dstrect)
-                        .expect("failed to copy tile");
-                }
-            }
-        })
-        .map_err(|e| e.to_string())?;
+mod backend;
+use backend::{Backend, Input, Mouse};
 
-    canvas.set_draw_color(Color::RGBA(0, 0, 0, 255));
+mod cp437;
+use cp437::Cp437;
 
-    Ok(())
-}
+mod font;
 
-fn draw_tile(
-    canvas: &mut Canvas<Window>,
-    frame_texture: &mut Texture,
-    tiles_texture: &mut Texture,
-    tile: &Tile,
-) -> Result<(), String> {
-    canvas
-        .with_texture_canvas(frame_texture, |texture_canvas| {
-            let coords = Coords::from(tile.code_point);
-            let srcrect = Rect::new(
-                (TILE_SIZE.0 as i32) * coords.row,
-                (TILE_SIZE.1 as i32) * coords.col,
-                TILE_SIZE.0,
-                TILE_SIZE.1,
-            );
-            let dstrect = Rect::new(
-                (tile.row * TILE_SIZE.0) as i32,
-                (tile.col * TILE_SIZE.1) as i32,
-                TILE_SIZE.0,
-                TILE_SIZE.1,
-            );
-            let Color { r, g, b, .. } = tile.foreground;
-            tiles_texture.set_color_mod(r, g, b);
-            texture_canvas.set_draw_color(tile.background);
-            texture_canvas
-                .fill_rect(Some(dstrect))
-                .expect("failed to draw rect");
-            texture_canvas
-                .copy(&tiles_texture, srcrect, dstrect)
-                .expect("failed to copy tile");
-        })
-        .map_err(|e| e.to_string())?;
-
-    canvas.set_draw_color(Color::RGBA(0, 0, 0, 255));
+mod glyph;
 
-    Ok(())
-}
+mod console;
+use console::{BlitOptions, Console, FrameStyle};
 
-#[derive(Debug, Copy, Clone)]
-enum Animation {
-    Blink(f32),
-    VerticalShift,
-    HorizontalShift,
-    ColorShift(f32, Color, Color),
-}
+mod color;
 
-#[derive(Debug, Clone)]
-struct Tile {
-    row: u32,
-    col: u32,
-    code_point: Cp437,
-    foreground: Color,
-    background: Color,
-    dirty: bool,
-    animations: Vec<Animation>,
-}
+mod config;
+use config::Config;
 
-impl Tile {
-    pub fn dirty(&self) -> bool {
-        self.dirty
-    }
-}
+mod headless;
+use headless::HeadlessBackend;
 
-impl Default for Tile {
-    fn default() -> Self {
-        Self {
-            row: 0,
-            col: 0,
-            code_point: Cp437::QuestionMark,
-            foreground: Color::RGBA(255, 0, 0, 255),
-            background: Color::RGBA(0, 0, 255, 255),
-            dirty: true,
-            animations: vec![],
-        }
-    }
-}
+mod keyframes;
+use keyframes::AnimationLibrary;
 
-#[derive(Debug, Default)]
-struct Console {
-    width: u32,
-    height: u32,
-    tiles: Vec<Tile>,
-}
+mod layers;
+use layers::{Layer, Layers};
 
-impl Console {
-    pub fn new(width: u32, height: u32) -> Self {
-        let mut tiles = Vec::new();
-        for col in 0..height {
-            for row in 0..width {
-                tiles.push(Tile {
-                    row,
-                    col,
-                    ..Default::default()
-                })
-            }
-        }
-        Self {
-            width,
-            height,
-            tiles,
-        }
-    }
-    /*
-        // TODO: pass by reference?
-        pub fn dirty_tiles(&self) -> impl Iterator<Item = Tile> {
-            self.tiles.into_iter().filter(|t| t.dirty)
-        }
-    */
-    pub fn tiles(&self) -> &Vec<Tile> {
-        &self.tiles
-    }
+mod markup;
+use markup::MarkupError;
 
-    pub fn tiles_mut(&mut self) -> &mut Vec<Tile> {
-        &mut self.tiles
-    }
+mod palette;
+use palette::{Palette, PaletteSys};
 
-    pub fn width(&self) -> u32 {
-        self.width
-    }
+mod particles;
+use particles::{
+    Emitter, EmitterSys, Particle, ParticleDrawSys, ParticleStyle, ParticleSys, Position,
+    PARTICLE_LAYER,
+};
 
-    pub fn height(&self) -> u32 {
-        self.height
-    }
+mod screenshot;
+use screenshot::Screenshots;
 
-    pub fn reset_tiles(&mut self) {
-        for t in &mut self.tiles {
-            t.dirty = false;
-        }
-    }
+mod sdl;
 
-    pub fn tile_mut(&mut self, x: u32, y: u32) -> Option<&mut Tile> {
-        if x > self.width || y > self.height {
-            return None;
-        }
-        let index = self.index(x, y);
-        Some(&mut self.tiles[index])
-    }
+#[cfg(unix)]
+mod terminal;
+#[cfg(unix)]
+use terminal::Terminal;
 
-    fn index(&self, x: u32, y: u32) -> usize {
-        (x + (y * self.width)) as usize
-    }
-}
+mod transition;
+use transition::{ActiveTransition, Direction, Transition, TransitionStyle, TransitionSys};
 
-use sdl2::render::{TextureCreator, WindowCanvas};
-use sdl2::video::WindowContext;
-use sdl2::{EventPump, Sdl};
+mod tileset;
+use tileset::TilesetDesc;
 
-/*
-struct Sdl2System<'r> {
-    sdl: Sdl,
-    canvas: WindowCanvas,
-    texture_creator: &'r TextureCreator<WindowContext>,
-    tiles_texture: Texture<'r>,
-    frame_texture: Texture<'r>,
-    event_pump: EventPump,
-    dstrect: Rect,
-}
-
-impl<'a, 'r> System<'a> for Sdl2System<'r> {
-    type SystemData = (Read<'a, Console>,
-                       Entities<'a>,
-                       ReadStorage<'a, Pos>);
+mod xp;
 
-    fn run(&mut self, (con, ent, pos): Self::SystemData) {
-        println!("{:?}", *con);
-
-        println!("Listing Entities:");
-        for (ent, pos) in (&ent, &pos).join() {
-            println!("{:?}", ent);
-        }
-    }
-}
-*/
+const TILESET_PATH: &str = "res/tileset.ron";
+const PALETTE_PATH: &str = "res/palette.ron";
+const ANIMATIONS_PATH: &str = "res/animations.ron";
+// Swapped in turn over the palette with F10
+const PALETTE_VARIANT_PATHS: [&str; 3] = [
+    "res/palettes/night.ron",
+    "res/palettes/colorblind.ron",
+    "res/palettes/hallucinating.ron",
+];
+const CONFIG_PATH: &str = "config.ron";
+const SCREENSHOT_DIR: &str = "screenshots";
+const CONSOLE_SIZE: (u32, u32) = (140, 60);
+const PLAYER_POSITION: (i32, i32) = (12, 14);
+// Palette swaps take turns playing these, for `TRANSITION_DURATION` seconds
+const TRANSITION_STYLES: [TransitionStyle; 7] = [
+    TransitionStyle::Fade,
+    TransitionStyle::Wipe(Direction::Right),
+    TransitionStyle::Wipe(Direction::Down),
+    TransitionStyle::Wipe(Direction::Left),
+    TransitionStyle::Wipe(Direction::Up),
+    TransitionStyle::Dissolve,
+    TransitionStyle::Scramble,
+];
+const TRANSITION_DURATION: f32 = 0.6;
+// Shown along the bottom of the UI layer
+const HINTS: &str = "Space randomize  F10 palette  F11 scaling  F12 screenshot  Esc quit";
+// Shown in a box in the top right of the UI layer, in markup
+const LEGEND: [&str; 3] = [
+    "[yellow]@[/] you",
+    "[water_2]≈[/] water",
+    "[lava_2]~[/] lava",
+];
+const LEGEND_SIZE: (u32, u32) = (16, 7);
 
 #[derive(Debug, Default)]
 struct State {
     quit: bool,
     randomize: bool,
+    screenshot: bool,
+    cycle_scaling: bool,
+    swap_palette: bool,
 }
 
 #[derive(Debug, Default)]
 struct PressedKeycodes(HashSet<Keycode>);
 
-struct SysA;
+/// The new console size in cells on frames where it changed, for reflowing layouts.
+#[derive(Debug, Default)]
+struct ConsoleResized(Option<(u32, u32)>);
+
+/// Palettes that can be swapped over the loaded one, and which one is, if any.
+#[derive(Default)]
+struct PaletteVariants {
+    variants: Vec<Palette>,
+    current: Option<usize>,
+    // How many times the palette has been swapped, to pick the next transition
+    swaps: usize,
+}
+
+#[derive(Default)]
+struct SysA {
+    // Keys held on the last frame, so holding a key only acts on it once
+    held: HashSet<Keycode>,
+}
 
 impl<'a> System<'a> for SysA {
-    type SystemData = (Read<'a, PressedKeycodes>, Write<'a, State>);
+    type SystemData = (
+        Read<'a, PressedKeycodes>,
+        Read<'a, ActiveTransition>,
+        Write<'a, State>,
+    );
 
     fn run(&mut self, data: Self::SystemData) {
-        let (keycodes, mut state) = data;
+        let (keycodes, transition, mut state) = data;
 
         state.quit = keycodes.0.contains(&Keycode::Escape);
         state.randomize = keycodes.0.contains(&Keycode::Space);
+        let pressed = |key| keycodes.0.contains(&key) && !self.held.contains(&key);
+        state.screenshot = pressed(Keycode::F12);
+        state.cycle_scaling = pressed(Keycode::F11);
+        state.swap_palette = pressed(Keycode::F10);
+        // No keys come through during a transition, and one held all through it
+        // shouldn't act again once it's over
+        if transition.0.is_none() {
+            self.held = keycodes.0.clone();
+        }
     }
 }
 
-fn main() -> Result<(), String> {
-    let sdl_context = sdl2::init()?;
-    let video_subsystem = sdl_context.video()?;
-    let _image_context = sdl2::image::init(InitFlag::JPG | InitFlag::PNG)?;
-    let window = video_subsystem
-        .window("rs_project", WINDOW_SIZE.0, WINDOW_SIZE.1)
-        .position_centered()
-        .resizable()
-        .hidden()
-        .build()
-        .map_err(|e| e.to_string())?;
-    let mut canvas = window
-        .into_canvas()
-        .accelerated()
-        .present_vsync()
-        .target_texture()
-        .build()
-        .map_err(|e| e.to_string())?;
-    let texture_creator = canvas.texture_creator();
-    let tiles_surface = Surface::from_file(Path::new("res/cooz_14x16.png"))?;
-    canvas.window_mut().set_icon(&tiles_surface);
-    let mut tiles_texture = texture_creator
-        .create_texture_from_surface(tiles_surface)
-        .map_err(|e| e.to_string())?;
-
-    let mut frame_texture = texture_creator
-        .create_texture_target(
-            PixelFormatEnum::RGBA8888,
-            TILE_SIZE.0 * CONSOLE_SIZE.0,
-            TILE_SIZE.1 * CONSOLE_SIZE.1,
-        )
-        .map_err(|e| e.to_string())?;
-    let mut event_pump = sdl_context.event_pump()?;
-    let mut dstrect = Rect::new(0, 0, 0, 0);
-    let mut fps = FPSCounter::new();
-    let mut dirty_window = false;
-
-    canvas.window_mut().show();
-    canvas.set_draw_color(Color::RGBA(0, 0, 0, 255));
+/// Draws the key hints on the bottom row of the UI layer and the legend in its top
+/// right corner. They're laid out again when the console is resized, and redrawn when
+/// the palette changes so the legend's colours follow it.
+struct UiSys {
+    // Palette indices of the foreground and background
+    colors: (u16, u16),
+    revision: Option<u64>,
+}
+
+impl<'a> System<'a> for UiSys {
+    type SystemData = (
+        Read<'a, ConsoleResized>,
+        Read<'a, Palette>,
+        Write<'a, Layers>,
+    );
+
+    fn run(&mut self, (resized, palette, mut layers): Self::SystemData) {
+        if self.revision == Some(palette.revision()) && resized.0.is_none() {
+            return;
+        }
+        self.revision = Some(palette.revision());
+        let ui = match layers.get_mut("ui") {
+            Some(layer) => &mut layer.console,
+            None => return,
+        };
+        let transparent = Color::RGBA(0, 0, 0, 0);
+        let (fg, bg) = self.colors;
+        ui.clear(Cp437::Space.into(), transparent, transparent);
+        let y = ui.height() as i32 - 1;
+        let text = format!(" {}", HINTS);
+        let chars = text.chars().chain(std::iter::repeat(' '));
+        for (x, c) in (0..ui.width() as i32).zip(chars) {
+            ui.put_indexed(x, y, c.into(), fg, bg, &palette);
+        }
+
+        match self.legend(&palette) {
+            Ok(legend) => {
+                let x = ui.width() as i32 - LEGEND_SIZE.0 as i32 - 1;
+                ui.blit(&legend, None, x, 1, BlitOptions::default());
+            }
+            Err(e) => eprintln!("drawing the legend failed: {}", e),
+        }
+    }
+}
+
+impl UiSys {
+    fn legend(&self, palette: &Palette) -> Result<Console, MarkupError> {
+        let black = Color::RGBA(0, 0, 0, 255);
+        let fg = palette.color(self.colors.0).unwrap_or(black);
+        let bg = palette.color(self.colors.1).unwrap_or(black);
+        let (width, height) = LEGEND_SIZE;
+        let mut legend = Console::new(width, height);
+        legend.clear(Cp437::Space.into(), fg, bg);
+        legend.draw_frame((0, 0), LEGEND_SIZE, FrameStyle::Double, fg, bg);
+        legend.print(2, 1, "Legend", fg, bg);
+        let rule = Cp437::BoxDrawingsLightHorizontal.into();
+        legend.draw_line((1, 2), (width as i32 - 2, 2), rule, fg, bg);
+        for (y, line) in LEGEND.iter().enumerate() {
+            legend.print_markup(2, 3 + y as i32, line, palette, fg, bg)?;
+        }
+        Ok(legend)
+    }
+}
 
+// Looks up a colour the game draws with, which the palette file has to have
+fn palette_index(palette: &Palette, name: &str) -> Result<u16, String> {
+    palette
+        .index(name)
+        .ok_or(format!("{}: no colour named {}", PALETTE_PATH, name))
+}
+
+/// Draws a pool of water and one of lava onto `map`, with colours that ripple as the
+/// palette cycles.
+fn draw_pools(map: &mut Console, palette: &Palette) -> Result<(), String> {
+    let pools = [
+        ((4, 4), Cp437::AlmostEqualTo, "water", "dark_blue"),
+        ((24, 4), Cp437::Tilde, "lava", "dark_red"),
+    ];
+    for &((left, top), glyph, cycle, background) in pools.iter() {
+        let bg = palette_index(palette, background)?;
+        let fgs = (0..4)
+            .map(|i| palette_index(palette, &format!("{}_{}", cycle, i)))
+            .collect::<Result<Vec<_>, _>>()?;
+        for y in 0..6 {
+            for x in 0..16 {
+                let fg = fgs[(x + y) as usize % fgs.len()];
+                map.put_indexed(left + x, top + y, glyph.into(), fg, bg, palette);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Embers rising off the lava pool drawn by `draw_pools` and fading out.
+fn lava_embers(palette: &Palette) -> Result<(Position, Emitter), String> {
+    let color = |name| {
+        palette
+            .get(name)
+            .ok_or(format!("{}: no colour named {}", PALETTE_PATH, name))
+    };
+    let faded = Color {
+        a: 0,
+        ..color("lava_0")?
+    };
+    let style = ParticleStyle {
+        glyphs: vec![Cp437::Asterisk.into(), Cp437::FullStop.into()],
+        gradient: vec![color("yellow")?, color("lava_2")?, faded],
+        gravity: -1.0,
+    };
+    let mut emitter = Emitter::new(Arc::new(style));
+    emitter.rate = 6.0;
+    emitter.direction = -FRAC_PI_2;
+    emitter.spread = 0.3;
+    emitter.speed = (1.0, 3.0);
+    emitter.lifetime = (0.5, 1.5);
+    emitter.area = (16.0, 1.0);
+    // So the pool isn't bare while the rate catches up
+    emitter.burst(12);
+    Ok((Position { x: 24.0, y: 4.0 }, emitter))
+}
+
+fn setup_world() -> Result<(World, Dispatcher<'static, 'static>), String> {
     let mut world = World::new();
     world.register::<Vel>();
     world.register::<Pos>();
+    world.register::<Position>();
+    world.register::<Emitter>();
+    world.register::<Particle>();
 
     world.insert(Console::new(CONSOLE_SIZE.0, CONSOLE_SIZE.1));
+    let palette = Palette::load(Path::new(PALETTE_PATH))?;
+    let mut layers = Layers::default();
+    let mut map = Console::new(CONSOLE_SIZE.0, CONSOLE_SIZE.1);
+    draw_pools(&mut map, &palette)?;
+    let (position, embers) = lava_embers(&palette)?;
+    layers.push(Layer::new("map", map));
+    let transparent = Color::RGBA(0, 0, 0, 0);
+    let mut particles = Console::new(CONSOLE_SIZE.0, CONSOLE_SIZE.1);
+    particles.clear(Cp437::Space.into(), transparent, transparent);
+    layers.push(Layer::new(PARTICLE_LAYER, particles));
+    let mut ui = Console::new(CONSOLE_SIZE.0, CONSOLE_SIZE.1);
+    ui.clear(Cp437::Space.into(), transparent, transparent);
+    layers.push(Layer::new("ui", ui));
+    world.insert(layers);
+    let ui = UiSys {
+        colors: (
+            palette_index(&palette, "grey")?,
+            palette_index(&palette, "dark_grey")?,
+        ),
+        revision: None,
+    };
+    let variants = PALETTE_VARIANT_PATHS
+        .iter()
+        .map(|path| Palette::load_variant(Path::new(path), &palette))
+        .collect::<Result<_, _>>()?;
+    world.insert(palette);
+    world.insert(PaletteVariants {
+        variants,
+        current: None,
+        swaps: 0,
+    });
+    world.insert(AnimationLibrary::load(Path::new(ANIMATIONS_PATH))?);
     world.insert(State {
         quit: false,
         randomize: false,
+        screenshot: false,
+        cycle_scaling: false,
+        swap_palette: false,
     });
-    world.insert(PressedKeycodes);
-
-    println!("{:?}", Coords::from(Cp437::from('G')));
-
-    let mut dispatcher = DispatcherBuilder::new().with(SysA, "sys_a", &[]).build();
+    world.insert(PressedKeycodes::default());
+    world.insert(Mouse::default());
+    world.insert(ConsoleResized::default());
+    world.insert(FrameTime::default());
+    world.insert(ActiveTransition::default());
+
+    let mut dispatcher = DispatcherBuilder::new()
+        .with(SysA::default(), "sys_a", &[])
+        .with(ui, "ui", &[])
+        .with(PaletteSys::default(), "palette", &[])
+        .with(EmitterSys, "emitters", &[])
+        .with(ParticleSys, "particles", &["emitters"])
+        .with(ParticleDrawSys::default(), "particle_draw", &["particles"])
+        .build();
 
     dispatcher.setup(&mut world);
     world.create_entity().with(Vel(2.0)).with(Pos(0.0)).build();
     world.create_entity().with(Vel(4.0)).with(Pos(1.6)).build();
     world.create_entity().with(Vel(1.5)).with(Pos(5.4)).build();
     world.create_entity().with(Pos(2.0)).build();
+    world.create_entity().with(position).with(embers).build();
     dispatcher.dispatch(&mut world);
 
-    update_dstrect(&mut dstrect, canvas.window().size());
-
-    let mut last_fps_print = Instant::now();
-
-    'main: loop {
-        for event in event_pump.poll_iter() {
-            match event {
-                Event::Quit { .. } => break 'main,
-                Event::Window { win_event, .. } => match win_event {
-                    WindowEvent::Resized { .. } | WindowEvent::SizeChanged { .. } => {
-                        dirty_window = true;
-                    }
-                    _ => {}
-                },
-                _ => {}
-            }
-        }
+    Ok((world, dispatcher))
+}
 
-        let keycodes: HashSet<Keycode> = event_pump
-            .keyboard_state()
-            .pressed_scancodes()
-            .filter_map(Keycode::from_scancode)
-            .collect();
-        *world.fetch_mut::<PressedKeycodes>() = PressedKeycodes(keycodes);
+/// Runs one frame of game logic, returning false once the game should quit.
+fn update(world: &mut World, dispatcher: &mut Dispatcher, input: Input) -> bool {
+    // Nothing reacts to input until a transition finishes
+    let input = if world.fetch::<ActiveTransition>().0.is_some() {
+        Input::default()
+    } else {
+        input
+    };
+    *world.fetch_mut::<PressedKeycodes>() = PressedKeycodes(input.keycodes);
+    *world.fetch_mut::<Mouse>() = input.mouse;
 
-        // Update user input
-        dispatcher.dispatch(&mut world);
-        world.maintain();
+    // Update user input
+    dispatcher.dispatch(world);
+    world.maintain();
 
+    {
         let state = world.fetch::<State>();
         let mut console = world.fetch_mut::<Console>();
+        let mut layers = world.fetch_mut::<Layers>();
 
         use rayon::prelude::*;
 
-        if state.randomize {
-            console.tiles_mut().par_iter_mut().for_each(|tile| {
+        if let (true, Some(map)) = (state.randomize, layers.get_mut("map")) {
+            map.console.tiles_mut().par_iter_mut().for_each(|tile| {
                 if (random::<u32>() % 10) != 0 {
                     return;
                 }
-                tile.code_point = Cp437::from(random::<u32>() % (Cp437::Count as u32));
+                tile.code_point = Cp437::from(random::<u8>()).into();
+                tile.foreground_index = None;
+                tile.background_index = None;
                 tile.foreground = Color::RGBA(random::<u8>(), random::<u8>(), random::<u8>(), 255);
                 tile.background = Color::RGBA(
                     random::<u8>() % 32u8,
@@ -399,41 +404,262 @@ fn main() -> Result<(), String> {
                     random::<u8>() % 32u8,
                     255,
                 );
-                tile.dirty = true;
             });
         }
 
         if state.quit {
-            break 'main;
+            return false;
         }
 
-        for tile in console.tiles() {
-            if tile.dirty() {
-                draw_tile(&mut canvas, &mut frame_texture, &mut tiles_texture, &tile)?;
+        // The layers are recoloured straight away so the transition has something to
+        // go to, rather than waiting for the palette system
+        if state.swap_palette {
+            let mut palette = world.fetch_mut::<Palette>();
+            let mut variants = world.fetch_mut::<PaletteVariants>();
+            variants.current = match variants.current {
+                None if !variants.variants.is_empty() => Some(0),
+                Some(i) if i + 1 < variants.variants.len() => Some(i + 1),
+                _ => None,
+            };
+            let variant = variants.current.map(|i| &variants.variants[i]);
+            match palette.swap(variant) {
+                Ok(()) => {
+                    let from = console.clone();
+                    for layer in layers.iter_mut() {
+                        layer.console.recolor(&palette);
+                    }
+                    layers.compose(&mut console);
+                    let style = TRANSITION_STYLES[variants.swaps % TRANSITION_STYLES.len()];
+                    variants.swaps += 1;
+                    world.fetch_mut::<ActiveTransition>().0 = Some(Transition::new(
+                        from,
+                        console.clone(),
+                        style,
+                        TRANSITION_DURATION,
+                    ));
+                }
+                Err(e) => eprintln!("swapping palette failed: {}", e),
             }
         }
 
-        console.reset_tiles();
+        layers.compose(&mut console);
+    }
+
+    // Animate the composited console, leaving the layers as they are
+    AnimationSys.run_now(world);
+    TransitionSys.run_now(world);
+
+    true
+}
+
+/// Resizes the console and its layers, letting systems know on the next dispatch.
+fn resize_console(world: &mut World, size: (u32, u32)) {
+    let black = Color::RGBA(0, 0, 0, 255);
+    world
+        .fetch_mut::<Console>()
+        .resize(size, Cp437::Null.into(), black, black);
+    world.fetch_mut::<Layers>().resize(size);
+    *world.fetch_mut::<ConsoleResized>() = ConsoleResized(Some(size));
+}
+
+/// Draws the player on the map with the backend's player glyph, or an `@` if its glyph
+/// source doesn't name one.
+fn place_player(world: &mut World, backend: &dyn Backend) {
+    let player = backend
+        .glyph("player")
+        .unwrap_or_else(|| Cp437::CommercialAt.into());
+    if let Some(map) = world.fetch_mut::<Layers>().get_mut("map") {
+        let (x, y) = PLAYER_POSITION;
+        let (white, black) = (Color::RGBA(255, 255, 255, 255), Color::RGBA(0, 0, 0, 255));
+        map.console.put(x, y, player, white, black);
+    }
+}
+
+/// What the game loop needs besides the backend and the ECS.
+struct Session {
+    config: Config,
+    screenshots: Screenshots,
+    /// Resize the console to fill the window or terminal.
+    fit_console: bool,
+}
 
-        if dirty_window {
-            update_dstrect(&mut dstrect, canvas.window().size());
-            dirty_window = false;
+/// Runs the game loop on `backend` until the game or the backend quits.
+fn run(
+    backend: &mut dyn Backend,
+    world: &mut World,
+    dispatcher: &mut Dispatcher,
+    session: &mut Session,
+) -> Result<(), String> {
+    let mut size = None;
+    let mut last_frame = Instant::now();
+
+    backend.configure(&session.config)?;
+    place_player(world, backend);
+
+    loop {
+        let input = backend.poll_input()?;
+
+        let now = Instant::now();
+        world.fetch_mut::<FrameTime>().advance(now - last_frame);
+        last_frame = now;
+
+        *world.fetch_mut::<ConsoleResized>() = ConsoleResized(None);
+        if let (true, Some(fitting)) = (session.fit_console, backend.fitting_size()) {
+            let current = {
+                let console = world.fetch::<Console>();
+                (console.width(), console.height())
+            };
+            if fitting != current {
+                resize_console(world, fitting);
+            }
         }
 
-        canvas.clear();
-        canvas.copy(&frame_texture, None, dstrect)?;
-        canvas.present();
+        if input.quit || !update(world, dispatcher, input) {
+            break;
+        }
 
-        if Instant::now() - last_fps_print > Duration::new(5, 0) {
-            println!("fps: {}", fps.tick());
-            last_fps_print = Instant::now();
-        } else {
-            fps.tick();
+        let mut console = world.fetch_mut::<Console>();
+
+        if size != Some((console.width(), console.height())) {
+            size = Some((console.width(), console.height()));
+            backend.resize((console.width(), console.height()))?;
+        }
+
+        backend.present(&console)?;
+
+        if world.fetch::<State>().screenshot {
+            // A failed screenshot isn't worth quitting over
+            if let Err(e) = backend
+                .screenshot()
+                .and_then(|frame| session.screenshots.save(frame, &console))
+            {
+                eprintln!("screenshot failed: {}", e);
+            }
         }
+
+        if world.fetch::<State>().cycle_scaling {
+            session.config.scaling = session.config.scaling.next();
+            backend.configure(&session.config)?;
+            if let Err(e) = session.config.save(Path::new(CONFIG_PATH)) {
+                eprintln!("saving config failed: {}", e);
+            }
+        }
+
+        console.reset_tiles();
     }
 
-    canvas.window_mut().hide();
+    Ok(())
+}
 
+struct Options {
+    backend: String,
+    frames: u32,
+    output: Option<PathBuf>,
+    /// Save a text dump of the console with each screenshot.
+    screenshot_text: bool,
+    /// Resize the console to fill the window instead of scaling a fixed size.
+    fit_console: bool,
+    /// Keys pressed one per frame by the headless backend.
+    keys: Vec<Keycode>,
+    /// Replaces the map with one saved by `save_map`.
+    load_map: Option<PathBuf>,
+    /// Saves the map when the game quits.
+    save_map: Option<PathBuf>,
+}
+
+fn parse_args() -> Result<Options, String> {
+    let mut options = Options {
+        backend: "sdl".to_string(),
+        frames: 1,
+        output: None,
+        screenshot_text: false,
+        fit_console: false,
+        keys: Vec::new(),
+        load_map: None,
+        save_map: None,
+    };
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or(format!("{} needs a value", arg));
+        match arg.as_str() {
+            "--backend" => options.backend = value()?,
+            // Kept from before there was a choice of backends
+            "--terminal" => options.backend = "terminal".to_string(),
+            "--frames" => {
+                options.frames = value()?.parse().map_err(|e| format!("--frames: {}", e))?
+            }
+            "--output" => options.output = Some(PathBuf::from(value()?)),
+            "--screenshot-text" => options.screenshot_text = true,
+            "--fit-console" => options.fit_console = true,
+            "--load-map" => options.load_map = Some(PathBuf::from(value()?)),
+            "--save-map" => options.save_map = Some(PathBuf::from(value()?)),
+            "--keys" => {
+                for name in value()?.split(',') {
+                    let key = Keycode::from_name(name)
+                        .ok_or(format!("--keys: unknown key {:?}", name))?;
+                    options.keys.push(key);
+                }
+            }
+            _ => return Err(format!("unknown argument {:?}", arg)),
+        }
+    }
+    Ok(options)
+}
+
+fn main() -> Result<(), String> {
+    let options = parse_args()?;
+    let (mut world, mut dispatcher) = setup_world()?;
+    let desc = TilesetDesc::load(Path::new(TILESET_PATH))?;
+    let mut session = Session {
+        config: Config::load(Path::new(CONFIG_PATH))?,
+        screenshots: Screenshots::new(
+            PathBuf::from(SCREENSHOT_DIR),
+            options.screenshot_text,
+            desc.clone(),
+        ),
+        fit_console: options.fit_console,
+    };
+    if let Some(path) = &options.load_map {
+        let map = Console::load_ron(path)?;
+        if let Some(layer) = world.fetch_mut::<Layers>().get_mut("map") {
+            layer.console = map;
+        }
+    }
+
+    match options.backend.as_str() {
+        "sdl" => sdl::with_backend(&desc, |backend| {
+            run(backend, &mut world, &mut dispatcher, &mut session)
+        }),
+        #[cfg(unix)]
+        "terminal" => {
+            let mut terminal = Terminal::new().map_err(|e| e.to_string())?;
+            run(&mut terminal, &mut world, &mut dispatcher, &mut session)
+        }
+        #[cfg(not(unix))]
+        "terminal" => Err("the terminal backend needs a Unix terminal".to_string()),
+        "headless" => {
+            let mut headless = HeadlessBackend::new(&desc, options.frames)?;
+            for key in options.keys {
+                headless.push_input(Input {
+                    keycodes: Some(key).into_iter().collect(),
+                    ..Input::default()
+                });
+            }
+            run(&mut headless, &mut world, &mut dispatcher, &mut session)?;
+            match (&options.output, headless.frame()) {
+                (Some(path), Some(frame)) => frame.save_png(path),
+                _ => Ok(()),
+            }
+        }
+        other => Err(format!(
+            "unknown backend {:?}, expected sdl, terminal or headless",
+            other
+        )),
+    }?;
+
+    if let (Some(path), Some(layer)) = (&options.save_map, world.fetch::<Layers>().get("map")) {
+        layer.console.save_ron(path)?;
+    }
     Ok(())
 }
 
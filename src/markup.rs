@@ -0,0 +1,224 @@
+use std::fmt;
+
+use sdl2::pixels::Color;
+
+use crate::palette::Palette;
+
+/// A run of text sharing the same colours.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Span {
+    pub text: String,
+    pub foreground: Color,
+    pub background: Color,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MarkupError {
+    UnknownColor(String),
+    InvalidTag(String),
+    /// Byte offset of a `[` without a matching `]`.
+    Unclosed(usize),
+    /// Byte offset of a `[/]` with no open tag.
+    UnmatchedClose(usize),
+}
+
+impl fmt::Display for MarkupError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MarkupError::UnknownColor(name) => write!(f, "unknown colour {:?}", name),
+            MarkupError::InvalidTag(tag) => write!(f, "invalid tag [{}]", tag),
+            MarkupError::Unclosed(i) => write!(f, "unclosed tag at {}", i),
+            MarkupError::UnmatchedClose(i) => write!(f, "[/] at {} closes nothing", i),
+        }
+    }
+}
+
+impl std::error::Error for MarkupError {}
+
+/// Splits `text` into coloured spans.
+///
+/// `[red]` sets the foreground, `[on blue]` the background and `[red on blue]` both,
+/// until the matching `[/]`. Colour names are looked up in `palette`, and `[[` is a
+/// literal `[`.
+pub fn parse(
+    text: &str,
+    palette: &Palette,
+    fg: Color,
+    bg: Color,
+) -> Result<Vec<Span>, MarkupError> {
+    let mut stack = vec![(fg, bg)];
+    let mut spans = Vec::new();
+    let mut current = String::new();
+    let mut chars = text.char_indices().peekable();
+
+    while let Some((i, c)) = chars.next() {
+        if c != '[' {
+            current.push(c);
+            continue;
+        }
+        if let Some(&(_, '[')) = chars.peek() {
+            chars.next();
+            current.push('[');
+            continue;
+        }
+
+        let end = i + text[i..].find(']').ok_or(MarkupError::Unclosed(i))?;
+        while let Some(&(j, _)) = chars.peek() {
+            if j > end {
+                break;
+            }
+            chars.next();
+        }
+
+        let (fg, bg) = *stack.last().unwrap();
+        if !current.is_empty() {
+            spans.push(Span {
+                text: current.split_off(0),
+                foreground: fg,
+                background: bg,
+            });
+        }
+
+        let tag = &text[i + 1..end];
+        if tag == "/" {
+            if stack.len() == 1 {
+                return Err(MarkupError::UnmatchedClose(i));
+            }
+            stack.pop();
+        } else {
+            stack.push(parse_tag(tag, palette, fg, bg)?);
+        }
+    }
+
+    if !current.is_empty() {
+        let (fg, bg) = *stack.last().unwrap();
+        spans.push(Span {
+            text: current,
+            foreground: fg,
+            background: bg,
+        });
+    }
+
+    Ok(spans)
+}
+
+fn parse_tag(
+    tag: &str,
+    palette: &Palette,
+    fg: Color,
+    bg: Color,
+) -> Result<(Color, Color), MarkupError> {
+    let lookup = |name: &str| {
+        palette
+            .get(name)
+            .ok_or_else(|| MarkupError::UnknownColor(name.to_string()))
+    };
+    let words: Vec<&str> = tag.split_whitespace().collect();
+    match words.as_slice() {
+        [name] => Ok((lookup(name)?, bg)),
+        ["on", name] => Ok((fg, lookup(name)?)),
+        [fg_name, "on", bg_name] => Ok((lookup(fg_name)?, lookup(bg_name)?)),
+        _ => Err(MarkupError::InvalidTag(tag.to_string())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    fn palette() -> Palette {
+        Palette::load(Path::new("res/palette.ron")).unwrap()
+    }
+
+    // Text and colour names of each span, with `-` for the defaults
+    fn spans(text: &str) -> Result<Vec<(String, &'static str, &'static str)>, MarkupError> {
+        let palette = palette();
+        let name = |color: Color, default: Color| {
+            ["red", "yellow", "blue", "green"]
+                .iter()
+                .find(|name| palette.get(name) == Some(color))
+                .copied()
+                .unwrap_or_else(|| {
+                    assert_eq!(color, default);
+                    "-"
+                })
+        };
+        let (fg, bg) = (Color::RGB(1, 2, 3), Color::RGB(4, 5, 6));
+        Ok(parse(text, &palette, fg, bg)?
+            .into_iter()
+            .map(|span| {
+                (
+                    span.text,
+                    name(span.foreground, fg),
+                    name(span.background, bg),
+                )
+            })
+            .collect())
+    }
+
+    fn span(
+        text: &str,
+        fg: &'static str,
+        bg: &'static str,
+    ) -> (String, &'static str, &'static str) {
+        (text.to_string(), fg, bg)
+    }
+
+    #[test]
+    fn colours_tagged_runs() {
+        assert_eq!(
+            spans("You hit the [red]goblin[/] for [yellow]5[/] damage"),
+            Ok(vec![
+                span("You hit the ", "-", "-"),
+                span("goblin", "red", "-"),
+                span(" for ", "-", "-"),
+                span("5", "yellow", "-"),
+                span(" damage", "-", "-"),
+            ])
+        );
+    }
+
+    #[test]
+    fn escapes_brackets() {
+        assert_eq!(
+            spans("[[red] and [[[red]x[/]"),
+            Ok(vec![span("[red] and [", "-", "-"), span("x", "red", "-")])
+        );
+    }
+
+    #[test]
+    fn sets_backgrounds() {
+        assert_eq!(
+            spans("[red on blue]a[/][on blue]b"),
+            Ok(vec![span("a", "red", "blue"), span("b", "-", "blue")])
+        );
+    }
+
+    #[test]
+    fn closes_the_innermost_tag() {
+        assert_eq!(
+            spans("[red]a[on blue]b[green]c[/]d[/]e[/]f"),
+            Ok(vec![
+                span("a", "red", "-"),
+                span("b", "red", "blue"),
+                span("c", "green", "blue"),
+                span("d", "red", "blue"),
+                span("e", "red", "-"),
+                span("f", "-", "-"),
+            ])
+        );
+    }
+
+    #[test]
+    fn rejects_bad_markup() {
+        let unknown = |name: &str| Err(MarkupError::UnknownColor(name.to_string()));
+        assert_eq!(spans("[mauve]x"), unknown("mauve"));
+        assert_eq!(spans("[red on mauve]x"), unknown("mauve"));
+        let invalid = |tag: &str| Err(MarkupError::InvalidTag(tag.to_string()));
+        assert_eq!(spans("[]x"), invalid(""));
+        assert_eq!(spans("[red blue]x"), invalid("red blue"));
+        assert_eq!(spans("ab [red"), Err(MarkupError::Unclosed(3)));
+        assert_eq!(spans("[red]a[/]b[/]"), Err(MarkupError::UnmatchedClose(10)));
+    }
+}
@@ -0,0 +1,289 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::path::Path;
+
+use serde::Deserialize;
+
+use specs::prelude::*;
+
+use sdl2::pixels::Color;
+
+use crate::animation::FrameTime;
+use crate::layers::Layers;
+
+// A palette as written, see `res/palette.ron`
+#[derive(Deserialize)]
+struct PaletteFile {
+    colors: Vec<(String, (u8, u8, u8))>,
+    #[serde(default)]
+    cycles: Vec<CycleDesc>,
+}
+
+/// Rotates the colours from `first` to `last`, inclusive, by one index every
+/// `period` seconds.
+#[derive(Debug, Clone, Deserialize)]
+struct CycleDesc {
+    first: String,
+    last: String,
+    period: f32,
+}
+
+#[derive(Debug, Clone)]
+struct Cycle {
+    first: usize,
+    len: usize,
+    period: f32,
+    step: usize,
+}
+
+/// Colours that can be referred to by name, e.g. from markup tags, or by their index
+/// in the palette file from tiles, see `Console::put_indexed`.
+///
+/// Indexed tiles follow the palette when it's swapped or cycled, see `PaletteSys`.
+#[derive(Debug, Default)]
+pub struct Palette {
+    names: HashMap<String, usize>,
+    // Colours as loaded, for swapping back
+    loaded: Vec<Color>,
+    // Colours before cycling
+    base: Vec<Color>,
+    colors: Vec<Color>,
+    cycle_descs: Vec<CycleDesc>,
+    cycles: Vec<Cycle>,
+    // Bumped whenever `colors` changes
+    revision: u64,
+}
+
+impl Palette {
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let mut palette = Self::read(path)?;
+        palette.cycles = palette
+            .resolve_cycles(&palette.cycle_descs)
+            .map_err(|e| format!("{}: {}", path.display(), e))?;
+        Ok(palette)
+    }
+
+    /// Loads a palette to `swap` over `base`. It only needs to list the colours it
+    /// changes, but every colour it lists and every one its cycles name has to be in
+    /// `base`.
+    pub fn load_variant(path: &Path, base: &Palette) -> Result<Self, String> {
+        let variant = Self::read(path)?;
+        base.swapped(&variant)
+            .map_err(|e| format!("{}: {}", path.display(), e))?;
+        Ok(variant)
+    }
+
+    fn read(path: &Path) -> Result<Self, String> {
+        let file = File::open(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        let desc: PaletteFile =
+            ron::de::from_reader(file).map_err(|e| format!("{}: {}", path.display(), e))?;
+        let mut names = HashMap::new();
+        let mut loaded = Vec::new();
+        for (name, (r, g, b)) in desc.colors {
+            if names.insert(name.clone(), loaded.len()).is_some() {
+                return Err(format!("{}: {} is defined twice", path.display(), name));
+            }
+            loaded.push(Color::RGBA(r, g, b, 255));
+        }
+        Ok(Self {
+            names,
+            base: loaded.clone(),
+            colors: loaded.clone(),
+            loaded,
+            cycle_descs: desc.cycles,
+            cycles: Vec::new(),
+            revision: 0,
+        })
+    }
+
+    pub fn get(&self, name: &str) -> Option<Color> {
+        self.names.get(name).map(|&index| self.colors[index])
+    }
+
+    pub fn index(&self, name: &str) -> Option<u16> {
+        self.names.get(name).map(|&index| index as u16)
+    }
+
+    pub fn color(&self, index: u16) -> Option<Color> {
+        self.colors.get(index as usize).copied()
+    }
+
+    /// Changes whenever colours do, so anything following the palette can tell when to
+    /// update.
+    pub fn revision(&self) -> u64 {
+        self.revision
+    }
+
+    /// Replaces the colours `variant` names with its own and adds its cycles. Colours
+    /// it leaves out go back to how they were loaded, as does everything if `variant`
+    /// is `None`. Nothing changes if `variant` names a colour this palette doesn't have.
+    pub fn swap(&mut self, variant: Option<&Palette>) -> Result<(), String> {
+        let (base, cycles) = match variant {
+            Some(variant) => self.swapped(variant)?,
+            None => (self.loaded.clone(), self.resolve_cycles(&self.cycle_descs)?),
+        };
+        self.colors = base.clone();
+        self.base = base;
+        self.cycles = cycles;
+        self.revision += 1;
+        Ok(())
+    }
+
+    // The colours and cycles this palette would have with `variant` swapped in
+    fn swapped(&self, variant: &Palette) -> Result<(Vec<Color>, Vec<Cycle>), String> {
+        let mut base = self.loaded.clone();
+        for (name, &index) in &variant.names {
+            let own = self
+                .names
+                .get(name)
+                .ok_or(format!("no colour named {}", name))?;
+            base[*own] = variant.loaded[index];
+        }
+        let mut cycles = self.cycle_descs.clone();
+        cycles.extend(variant.cycle_descs.iter().cloned());
+        Ok((base, self.resolve_cycles(&cycles)?))
+    }
+
+    // Resolves cycle names to index ranges in this palette
+    fn resolve_cycles(&self, descs: &[CycleDesc]) -> Result<Vec<Cycle>, String> {
+        let index = |name: &str| {
+            self.names
+                .get(name)
+                .copied()
+                .ok_or(format!("no colour named {}", name))
+        };
+        let mut cycles = Vec::new();
+        for desc in descs {
+            let (first, last) = (index(&desc.first)?, index(&desc.last)?);
+            if last < first {
+                return Err(format!("{} comes before {}", desc.last, desc.first));
+            }
+            cycles.push(Cycle {
+                first,
+                len: last - first + 1,
+                period: desc.period,
+                step: 0,
+            });
+        }
+        Ok(cycles)
+    }
+
+    /// Rotates the cycling colours to where they should be `elapsed` seconds in.
+    pub fn cycle(&mut self, elapsed: f32) {
+        for cycle in self.cycles.iter_mut() {
+            if cycle.period <= 0.0 {
+                continue;
+            }
+            let step = (elapsed / cycle.period) as usize % cycle.len;
+            if step == cycle.step {
+                continue;
+            }
+            cycle.step = step;
+            let range = cycle.first..cycle.first + cycle.len;
+            self.colors[range.clone()].copy_from_slice(&self.base[range.clone()]);
+            self.colors[range].rotate_right(step);
+            self.revision += 1;
+        }
+    }
+}
+
+/// Cycles the palette and recolours indexed tiles on every layer when it changes.
+#[derive(Default)]
+pub struct PaletteSys {
+    revision: Option<u64>,
+}
+
+impl<'a> System<'a> for PaletteSys {
+    type SystemData = (Read<'a, FrameTime>, Write<'a, Palette>, Write<'a, Layers>);
+
+    fn run(&mut self, (time, mut palette, mut layers): Self::SystemData) {
+        palette.cycle(time.elapsed);
+        if self.revision == Some(palette.revision()) {
+            return;
+        }
+        self.revision = Some(palette.revision());
+        for layer in layers.iter_mut() {
+            layer.console.recolor(&palette);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn base() -> Palette {
+        Palette::load(Path::new("res/palette.ron")).unwrap()
+    }
+
+    fn colors(palette: &Palette) -> Vec<Color> {
+        (0..).map_while(|i| palette.color(i)).collect()
+    }
+
+    #[test]
+    fn swaps_back_to_the_loaded_colours() {
+        let mut palette = base();
+        let loaded = colors(&palette);
+        let night = Palette::load_variant(Path::new("res/palettes/night.ron"), &palette).unwrap();
+
+        palette.swap(Some(&night)).unwrap();
+        assert_eq!(palette.revision(), 1);
+        assert_eq!(palette.get("red"), Some(Color::RGBA(120, 30, 50, 255)));
+        // Left out of the variant, so kept as loaded
+        assert_eq!(palette.get("black"), Some(Color::RGBA(0, 0, 0, 255)));
+        assert_eq!(palette.get("lava_2"), Some(Color::RGBA(255, 140, 20, 255)));
+
+        palette.swap(None).unwrap();
+        assert_eq!(palette.revision(), 2);
+        assert_eq!(colors(&palette), loaded);
+    }
+
+    #[test]
+    fn rejects_variants_with_unknown_colours() {
+        let path = std::env::temp_dir().join(format!("palette-test-{}.ron", std::process::id()));
+        std::fs::write(
+            &path,
+            r#"(colors: [("red", (1, 2, 3)), ("mauve", (4, 5, 6))])"#,
+        )
+        .unwrap();
+        let mut palette = base();
+        let error = Palette::load_variant(&path, &palette).unwrap_err();
+        assert!(error.contains("no colour named mauve"), "{}", error);
+
+        let variant = Palette::read(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        let loaded = colors(&palette);
+        assert!(palette.swap(Some(&variant)).is_err());
+        assert_eq!(colors(&palette), loaded);
+        assert_eq!(palette.revision(), 0);
+    }
+
+    #[test]
+    fn cycles_at_each_period() {
+        let mut palette = base();
+        let water: Vec<Color> = (15..19).map(|i| palette.color(i).unwrap()).collect();
+        let lava: Vec<Color> = (19..23).map(|i| palette.color(i).unwrap()).collect();
+
+        palette.cycle(0.2);
+        assert_eq!(palette.revision(), 0);
+
+        // Lava moves every 0.25 seconds, water every 0.4
+        palette.cycle(0.3);
+        assert_eq!(palette.revision(), 1);
+        assert_eq!(palette.get("lava_0"), Some(lava[3]));
+        assert_eq!(palette.get("lava_1"), Some(lava[0]));
+        assert_eq!(palette.get("water_0"), Some(water[0]));
+
+        palette.cycle(0.45);
+        assert_eq!(palette.revision(), 2);
+        assert_eq!(palette.get("lava_0"), Some(lava[3]));
+        assert_eq!(palette.get("water_0"), Some(water[3]));
+        assert_eq!(palette.get("water_1"), Some(water[0]));
+
+        // Both move, lava all the way round
+        palette.cycle(1.0);
+        assert_eq!(palette.revision(), 4);
+        assert_eq!(palette.get("lava_0"), Some(lava[0]));
+        assert_eq!(palette.get("water_0"), Some(water[2]));
+    }
+}
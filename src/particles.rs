@@ -0,0 +1,305 @@
+use std::f32::consts::PI;
+use std::sync::Arc;
+
+use rand::prelude::*;
+
+use specs::prelude::*;
+use specs_derive::Component;
+
+use sdl2::pixels::Color;
+
+use crate::animation::FrameTime;
+use crate::color;
+use crate::cp437::Cp437;
+use crate::glyph::Glyph;
+use crate::layers::Layers;
+
+/// The layer particles are drawn on.
+pub const PARTICLE_LAYER: &str = "particles";
+
+/// Where an entity is, in cells.
+#[derive(Debug, Copy, Clone, PartialEq, Component)]
+#[storage(VecStorage)]
+pub struct Position {
+    pub x: f32,
+    pub y: f32,
+}
+
+/// How particles look and move, shared by every particle an emitter makes.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParticleStyle {
+    /// Shown in turn over the particle's lifetime.
+    pub glyphs: Vec<Glyph>,
+    /// Colours spread evenly over the particle's lifetime and eased between. Alpha
+    /// fades the particle into what's below it.
+    pub gradient: Vec<Color>,
+    /// Downward acceleration in cells per second squared, negative to rise.
+    pub gravity: f32,
+}
+
+impl ParticleStyle {
+    fn at(&self, t: f32) -> (Glyph, Color) {
+        let t = t.clamp(0.0, 1.0);
+        let glyph = match self.glyphs.len() {
+            0 => Cp437::Asterisk.into(),
+            n => self.glyphs[((t * n as f32) as usize).min(n - 1)],
+        };
+        let color = match self.gradient.len() {
+            0 => Color::RGBA(255, 255, 255, 255),
+            1 => self.gradient[0],
+            n => {
+                let scaled = t * (n - 1) as f32;
+                let i = (scaled as usize).min(n - 2);
+                color::lerp(self.gradient[i], self.gradient[i + 1], scaled - i as f32)
+            }
+        };
+        (glyph, color)
+    }
+}
+
+/// A glyph moving freely over the console until its lifetime runs out.
+#[derive(Debug, Clone, Component)]
+#[storage(DenseVecStorage)]
+pub struct Particle {
+    /// In cells, drawn in the cell it falls within.
+    pub position: (f32, f32),
+    /// In cells per second.
+    pub velocity: (f32, f32),
+    /// Seconds since the particle was made.
+    pub age: f32,
+    /// Seconds the particle lasts.
+    pub lifetime: f32,
+    pub style: Arc<ParticleStyle>,
+}
+
+/// Makes particles around an entity's `Position`, or around the console's origin for
+/// entities without one.
+#[derive(Debug, Clone, Component)]
+#[storage(DenseVecStorage)]
+pub struct Emitter {
+    pub style: Arc<ParticleStyle>,
+    /// Particles made per second.
+    pub rate: f32,
+    /// Radians clockwise from the right that particles head off in.
+    pub direction: f32,
+    /// Radians either side of `direction` particles can head off in.
+    pub spread: f32,
+    /// Smallest and largest speed, in cells per second.
+    pub speed: (f32, f32),
+    /// Shortest and longest lifetime, in seconds.
+    pub lifetime: (f32, f32),
+    /// Top left of the area particles start in, relative to the entity.
+    pub offset: (f32, f32),
+    /// Width and height of the area particles start in, in cells.
+    pub area: (f32, f32),
+    // Particles owed by the rate and bursts but not made yet
+    pending: f32,
+}
+
+impl Emitter {
+    /// An emitter making nothing until given a rate or a burst, sending particles off
+    /// in every direction at one to four cells per second for a second.
+    pub fn new(style: Arc<ParticleStyle>) -> Self {
+        Self {
+            style,
+            rate: 0.0,
+            direction: 0.0,
+            spread: PI,
+            speed: (1.0, 4.0),
+            lifetime: (1.0, 1.0),
+            offset: (0.0, 0.0),
+            area: (0.0, 0.0),
+            pending: 0.0,
+        }
+    }
+
+    /// Makes `count` particles on the next frame.
+    pub fn burst(&mut self, count: u32) {
+        self.pending += count as f32;
+    }
+
+    fn spawn(&self, origin: (f32, f32), rng: &mut impl Rng) -> Particle {
+        let between =
+            |rng: &mut dyn RngCore, (low, high): (f32, f32)| low + rng.gen::<f32>() * (high - low);
+        let angle = self.direction + between(rng, (-self.spread, self.spread));
+        let speed = between(rng, self.speed);
+        Particle {
+            position: (
+                origin.0 + self.offset.0 + between(rng, (0.0, self.area.0)),
+                origin.1 + self.offset.1 + between(rng, (0.0, self.area.1)),
+            ),
+            velocity: (angle.cos() * speed, angle.sin() * speed),
+            age: 0.0,
+            lifetime: between(rng, self.lifetime),
+            style: self.style.clone(),
+        }
+    }
+}
+
+/// Makes the particles owed by each emitter since the last frame.
+pub struct EmitterSys;
+
+impl<'a> System<'a> for EmitterSys {
+    type SystemData = (
+        Entities<'a>,
+        Read<'a, FrameTime>,
+        ReadStorage<'a, Position>,
+        WriteStorage<'a, Emitter>,
+        WriteStorage<'a, Particle>,
+    );
+
+    fn run(&mut self, (entities, time, positions, mut emitters, mut particles): Self::SystemData) {
+        let mut rng = thread_rng();
+        let mut spawned = Vec::new();
+        for (emitter, position) in (&mut emitters, positions.maybe()).join() {
+            emitter.pending += emitter.rate * time.delta;
+            let origin = position.map_or((0.0, 0.0), |p| (p.x, p.y));
+            while emitter.pending >= 1.0 {
+                spawned.push(emitter.spawn(origin, &mut rng));
+                emitter.pending -= 1.0;
+            }
+        }
+        for particle in spawned {
+            entities
+                .build_entity()
+                .with(particle, &mut particles)
+                .build();
+        }
+    }
+}
+
+/// Moves particles in parallel and removes them once their lifetime is over.
+pub struct ParticleSys;
+
+impl<'a> System<'a> for ParticleSys {
+    type SystemData = (
+        Entities<'a>,
+        Read<'a, FrameTime>,
+        WriteStorage<'a, Particle>,
+    );
+
+    fn run(&mut self, (entities, time, mut particles): Self::SystemData) {
+        let dt = time.delta;
+        (&mut particles).par_join().for_each(|particle| {
+            particle.velocity.1 += particle.style.gravity * dt;
+            particle.position.0 += particle.velocity.0 * dt;
+            particle.position.1 += particle.velocity.1 * dt;
+            particle.age += dt;
+        });
+        for (entity, particle) in (&entities, &particles).join() {
+            if particle.age >= particle.lifetime {
+                // Only fails for entities that are already dead
+                let _ = entities.delete(entity);
+            }
+        }
+    }
+}
+
+/// Draws particles onto `PARTICLE_LAYER`, clearing the cells drawn on the last frame.
+#[derive(Default)]
+pub struct ParticleDrawSys {
+    drawn: Vec<(i32, i32)>,
+}
+
+impl<'a> System<'a> for ParticleDrawSys {
+    type SystemData = (ReadStorage<'a, Particle>, Write<'a, Layers>);
+
+    fn run(&mut self, (particles, mut layers): Self::SystemData) {
+        let layer = match layers.get_mut(PARTICLE_LAYER) {
+            Some(layer) => layer,
+            None => return,
+        };
+        let transparent = Color::RGBA(0, 0, 0, 0);
+        for (x, y) in self.drawn.drain(..) {
+            layer
+                .console
+                .put(x, y, Cp437::Space.into(), transparent, transparent);
+        }
+        for particle in particles.join() {
+            // Deleted particles are still stored until the world is maintained
+            if particle.age >= particle.lifetime {
+                continue;
+            }
+            let (x, y) = (
+                particle.position.0.floor() as i32,
+                particle.position.1.floor() as i32,
+            );
+            let (glyph, fg) = particle.style.at(particle.age / particle.lifetime);
+            layer.console.put(x, y, glyph, fg, transparent);
+            self.drawn.push((x, y));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::console::Console;
+    use crate::layers::Layer;
+
+    #[test]
+    fn particles_move_fade_and_die() {
+        let mut world = World::new();
+        world.register::<Particle>();
+        world.insert(FrameTime {
+            delta: 0.25,
+            elapsed: 0.0,
+        });
+        let mut console = Console::new(10, 3);
+        let transparent = Color::RGBA(0, 0, 0, 0);
+        console.clear(Cp437::Space.into(), transparent, transparent);
+        let mut layers = Layers::default();
+        layers.push(Layer::new(PARTICLE_LAYER, console));
+        world.insert(layers);
+        let style = ParticleStyle {
+            glyphs: vec![Cp437::Asterisk.into()],
+            gradient: vec![Color::RGBA(255, 0, 0, 255), Color::RGBA(255, 0, 0, 0)],
+            gravity: 0.0,
+        };
+        world
+            .create_entity()
+            .with(Particle {
+                position: (0.5, 1.5),
+                velocity: (4.0, 0.0),
+                age: 0.0,
+                lifetime: 1.0,
+                style: Arc::new(style),
+            })
+            .build();
+
+        let mut draw = ParticleDrawSys::default();
+        let mut step = |world: &mut World| {
+            ParticleSys.run_now(world);
+            draw.run_now(world);
+            world.maintain();
+        };
+        let tile = |world: &World, x| {
+            let layers = world.fetch::<Layers>();
+            let tile = layers
+                .get(PARTICLE_LAYER)
+                .unwrap()
+                .console
+                .tile(x, 1)
+                .unwrap();
+            (tile.code_point, tile.foreground.a)
+        };
+        let asterisk = Glyph::from(Cp437::Asterisk);
+        let space = Glyph::from(Cp437::Space);
+
+        // A cell further right each step, and fainter
+        let mut alpha = 255;
+        for x in 1..4 {
+            step(&mut world);
+            let (glyph, a) = tile(&world, x);
+            assert_eq!(glyph, asterisk);
+            assert!(a < alpha, "{} isn't fainter than {}", a, alpha);
+            assert_eq!(tile(&world, x - 1).0, space);
+            alpha = a;
+        }
+
+        // Gone once its lifetime is up, along with what was drawn of it
+        step(&mut world);
+        assert_eq!(world.read_storage::<Particle>().join().count(), 0);
+        assert_eq!(tile(&world, 3).0, space);
+    }
+}
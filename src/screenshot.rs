@@ -0,0 +1,107 @@
+use std::fs;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::console::Console;
+use crate::headless::{RgbaImage, SoftwareRenderer};
+use crate::tileset::TilesetDesc;
+
+/// Saves screenshots as timestamped PNGs, optionally with a text dump of the console.
+pub struct Screenshots {
+    dir: PathBuf,
+    text: bool,
+    tileset: TilesetDesc,
+    // For backends that can't read back what they drew
+    renderer: Option<SoftwareRenderer>,
+}
+
+impl Screenshots {
+    pub fn new(dir: PathBuf, text: bool, tileset: TilesetDesc) -> Self {
+        Self {
+            dir,
+            text,
+            tileset,
+            renderer: None,
+        }
+    }
+
+    /// Writes `frame`, or `console` rendered with the tileset if there is no frame,
+    /// returning the path of the PNG.
+    pub fn save(&mut self, frame: Option<RgbaImage>, console: &Console) -> Result<PathBuf, String> {
+        let image = match frame {
+            Some(image) => image,
+            None => {
+                if self.renderer.is_none() {
+                    self.renderer = Some(SoftwareRenderer::new(&self.tileset)?);
+                }
+                self.renderer.as_ref().unwrap().render(console)
+            }
+        };
+
+        fs::create_dir_all(&self.dir).map_err(|e| format!("{}: {}", self.dir.display(), e))?;
+        let stem = timestamp();
+        let mut path = self.dir.join(format!("{}.png", stem));
+        // Several screenshots in the same second get numbered
+        let mut n = 1;
+        while path.exists() {
+            path = self.dir.join(format!("{}-{}.png", stem, n));
+            n += 1;
+        }
+        image.save_png(&path)?;
+
+        if self.text {
+            let text_path = path.with_extension("txt");
+            fs::write(&text_path, console_text(console))
+                .map_err(|e| format!("{}: {}", text_path.display(), e))?;
+        }
+
+        Ok(path)
+    }
+}
+
+/// The characters of `console`, one line per row with trailing spaces trimmed.
+pub fn console_text(console: &Console) -> String {
+    let mut text = String::new();
+    if console.width() == 0 {
+        return text;
+    }
+    for row in console.tiles().chunks(console.width() as usize) {
+        let line: String = row
+            .iter()
+            .map(|tile| tile.code_point.display_char())
+            .collect();
+        text.push_str(line.trim_end());
+        text.push('\n');
+    }
+    text
+}
+
+// The current UTC time as `YYYY-MM-DD_HH-MM-SS`
+fn timestamp() -> String {
+    let secs = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    let (days, secs) = ((secs / 86400) as i64, secs % 86400);
+
+    // Days since 1970-01-01 to a civil date, after Howard Hinnant's civil_from_days
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    format!(
+        "{:04}-{:02}-{:02}_{:02}-{:02}-{:02}",
+        year,
+        month,
+        day,
+        secs / 3600,
+        secs / 60 % 60,
+        secs % 60
+    )
+}
@@ -0,0 +1,447 @@
+use std::time::{Duration, Instant};
+
+use sdl2::event::{Event, WindowEvent};
+use sdl2::image::InitFlag;
+use sdl2::keyboard::Keycode;
+use sdl2::mouse::MouseWheelDirection;
+use sdl2::pixels::{Color, PixelFormatEnum};
+use sdl2::rect::Rect;
+use sdl2::render::{Texture, TextureCreator, WindowCanvas};
+use sdl2::ttf::Sdl2TtfContext;
+use sdl2::video::WindowContext;
+use sdl2::EventPump;
+
+use fps_counter::FPSCounter;
+
+use crate::backend::{Backend, Input, Mouse};
+use crate::config::{Config, Letterbox, Scaling};
+use crate::console::{Console, Tile};
+use crate::font::FontAtlas;
+use crate::glyph::Glyph;
+use crate::headless::RgbaImage;
+use crate::tileset::{Tileset, TilesetDesc};
+
+const WINDOW_SIZE: (u32, u32) = (1280, 720);
+
+// Where a frame of `frame` pixels goes in a window of `window` pixels
+fn frame_dstrect(scaling: Scaling, window: (u32, u32), frame: (u32, u32)) -> Rect {
+    let (w, h) = window;
+    let (fw, fh) = frame;
+    let (dw, dh) = match scaling {
+        Scaling::Stretch => (w, h),
+        Scaling::Integer if w >= fw && h >= fh => {
+            let scale = (w / fw).min(h / fh);
+            (fw * scale, fh * scale)
+        }
+        Scaling::Integer | Scaling::Fit => {
+            let ratio = (w as f32 / fw as f32).min(h as f32 / fh as f32);
+            ((fw as f32 * ratio) as u32, (fh as f32 * ratio) as u32)
+        }
+    };
+    Rect::new(
+        (w.saturating_sub(dw) / 2) as i32,
+        (h.saturating_sub(dh) / 2) as i32,
+        dw.max(1),
+        dh.max(1),
+    )
+}
+
+// Only integer scaling keeps glyph edges sharp, the others are smoother filtered. That
+// includes integer scaling falling back on fitting a frame bigger than the window.
+fn scale_quality(scaling: Scaling, window: (u32, u32), frame: (u32, u32)) -> &'static str {
+    match scaling {
+        Scaling::Integer if window.0 >= frame.0 && window.1 >= frame.1 => "nearest",
+        Scaling::Integer | Scaling::Fit | Scaling::Stretch => "linear",
+    }
+}
+
+#[derive(Copy, Clone)]
+enum GlyphSource {
+    Tileset(Rect),
+    Font(Rect),
+}
+
+/// Opens a window drawing glyphs from the tileset described by `desc` and runs `f`
+/// with it. The SDL contexts have to outlive the textures, so they live here.
+pub fn with_backend<T>(
+    desc: &TilesetDesc,
+    f: impl FnOnce(&mut dyn Backend) -> Result<T, String>,
+) -> Result<T, String> {
+    let sdl_context = sdl2::init()?;
+    let video_subsystem = sdl_context.video()?;
+    let _image_context = sdl2::image::init(InitFlag::JPG | InitFlag::PNG)?;
+    let ttf_context = sdl2::ttf::init().map_err(|e| e.to_string())?;
+    let window = video_subsystem
+        .window("rs_project", WINDOW_SIZE.0, WINDOW_SIZE.1)
+        .position_centered()
+        .resizable()
+        .hidden()
+        .build()
+        .map_err(|e| e.to_string())?;
+    let canvas = window
+        .into_canvas()
+        .accelerated()
+        .present_vsync()
+        .target_texture()
+        .build()
+        .map_err(|e| e.to_string())?;
+    let texture_creator = canvas.texture_creator();
+    let event_pump = sdl_context.event_pump()?;
+
+    let mut backend = SdlBackend::new(canvas, &texture_creator, &ttf_context, event_pump, desc)?;
+    f(&mut backend)
+}
+
+/// Draws consoles into a target texture scaled to fit the window.
+pub struct SdlBackend<'ttf, 'r> {
+    canvas: WindowCanvas,
+    texture_creator: &'r TextureCreator<WindowContext>,
+    event_pump: EventPump,
+    tileset: Tileset<'r>,
+    font: Option<FontAtlas<'ttf, 'r>>,
+    frame_texture: Option<Texture<'r>>,
+    // The filtering `frame_texture` was made with
+    filter: &'static str,
+    config: Config,
+    dstrect: Rect,
+    dirty_window: bool,
+    redraw: bool,
+    mouse_cell: Option<(u32, u32)>,
+    fps: FPSCounter,
+    last_fps_print: Instant,
+}
+
+impl<'ttf, 'r> SdlBackend<'ttf, 'r> {
+    fn new(
+        mut canvas: WindowCanvas,
+        texture_creator: &'r TextureCreator<WindowContext>,
+        ttf_context: &'ttf Sdl2TtfContext,
+        event_pump: EventPump,
+        desc: &TilesetDesc,
+    ) -> Result<Self, String> {
+        let tiles_surface = desc.load_surface()?;
+        canvas.window_mut().set_icon(&tiles_surface);
+        let tileset = Tileset::new(texture_creator, desc, tiles_surface)?;
+        let font = match &desc.font {
+            Some(path) => Some(FontAtlas::new(
+                ttf_context,
+                texture_creator,
+                path,
+                tileset.tile_size(),
+            )?),
+            None => None,
+        };
+
+        canvas.window_mut().show();
+        canvas.set_draw_color(Color::RGBA(0, 0, 0, 255));
+
+        Ok(Self {
+            canvas,
+            texture_creator,
+            event_pump,
+            tileset,
+            font,
+            frame_texture: None,
+            filter: "",
+            config: Config::default(),
+            dstrect: Rect::new(0, 0, 0, 0),
+            dirty_window: true,
+            redraw: true,
+            mouse_cell: None,
+            fps: FPSCounter::new(),
+            last_fps_print: Instant::now(),
+        })
+    }
+
+    /// Maps a window position to the console cell drawn there, undoing the scaling
+    /// and letterboxing of the frame.
+    fn cell_at(&self, x: i32, y: i32) -> Option<(u32, u32)> {
+        let frame = self.frame_texture.as_ref()?.query();
+        if !self.dstrect.contains_point((x, y)) {
+            return None;
+        }
+        let (tile_w, tile_h) = self.tileset.tile_size();
+        let fx = (x - self.dstrect.x()) as u32 * frame.width / self.dstrect.width();
+        let fy = (y - self.dstrect.y()) as u32 * frame.height / self.dstrect.height();
+        Some((fx / tile_w, fy / tile_h))
+    }
+
+    fn draw_letterbox(&mut self, (frame_w, frame_h): (u32, u32)) -> Result<(), String> {
+        let (glyph, (r, g, b), background) = match self.config.letterbox {
+            Letterbox::Color(r, g, b) => {
+                self.canvas.set_draw_color(Color::RGB(r, g, b));
+                self.canvas.clear();
+                return Ok(());
+            }
+            Letterbox::Pattern {
+                glyph,
+                foreground,
+                background,
+            } => (glyph, foreground, background),
+        };
+        self.canvas
+            .set_draw_color(Color::RGB(background.0, background.1, background.2));
+        self.canvas.clear();
+        let srcrect = match glyph.sheet_glyph().and_then(|g| self.tileset.srcrect(g)) {
+            Some(rect) => rect,
+            None => return Ok(()),
+        };
+
+        // Line the pattern up with the frame's cells, at the same scale
+        let (tile_w, tile_h) = self.tileset.tile_size();
+        let cell_w = (tile_w * self.dstrect.width() / frame_w).max(1) as i32;
+        let cell_h = (tile_h * self.dstrect.height() / frame_h).max(1) as i32;
+        let (w, h) = self.canvas.window().size();
+        self.tileset.texture_mut().set_color_mod(r, g, b);
+        let mut y = self.dstrect.y().rem_euclid(cell_h) - cell_h;
+        while y < h as i32 {
+            let mut x = self.dstrect.x().rem_euclid(cell_w) - cell_w;
+            while x < w as i32 {
+                let dstrect = Rect::new(x, y, cell_w as u32, cell_h as u32);
+                self.canvas.copy(self.tileset.texture(), srcrect, dstrect)?;
+                x += cell_w;
+            }
+            y += cell_h;
+        }
+        Ok(())
+    }
+
+    fn draw_tiles<'a>(&mut self, tiles: impl Iterator<Item = &'a Tile>) -> Result<(), String> {
+        // Rasterising font glyphs can fail, so look glyphs up before drawing
+        let mut draws = Vec::new();
+        for tile in tiles {
+            let c = tile.code_point.to_char();
+            // A full atlas starts over, so draw what was looked up from it first
+            let evicts = match (c, &self.font) {
+                (Some(c), Some(font)) => font.evicts(c),
+                _ => false,
+            };
+            if evicts && !draws.is_empty() {
+                self.draw_batch(&draws)?;
+                draws.clear();
+            }
+            let font_rect = match (c, self.font.as_mut()) {
+                (Some(c), Some(font)) => font.srcrect(c)?,
+                _ => None,
+            };
+            // Without a font, or a glyph in it, fall back on the CP437 sheet
+            let source = match font_rect {
+                Some(rect) => Some(GlyphSource::Font(rect)),
+                None => tile
+                    .code_point
+                    .sheet_glyph()
+                    .and_then(|glyph| self.tileset.srcrect(glyph))
+                    .map(GlyphSource::Tileset),
+            };
+            draws.push((tile, source));
+        }
+        self.draw_batch(&draws)
+    }
+
+    fn draw_batch(&mut self, draws: &[(&Tile, Option<GlyphSource>)]) -> Result<(), String> {
+        let (tile_w, tile_h) = self.tileset.tile_size();
+        let tileset = &mut self.tileset;
+        let font = &mut self.font;
+        let frame_texture = match &mut self.frame_texture {
+            Some(texture) => texture,
+            None => return Err("console drawn before the backend was sized".to_string()),
+        };
+
+        self.canvas
+            .with_texture_canvas(frame_texture, |texture_canvas| {
+                for &(tile, source) in draws {
+                    let dstrect = Rect::new(
+                        (tile.row * tile_w) as i32,
+                        (tile.col * tile_h) as i32,
+                        tile_w,
+                        tile_h,
+                    );
+                    let Color { r, g, b, .. } = tile.foreground;
+                    texture_canvas.set_draw_color(tile.background);
+                    texture_canvas
+                        .fill_rect(Some(dstrect))
+                        .expect("failed to draw rect");
+                    match (source, font.as_mut()) {
+                        (Some(GlyphSource::Font(srcrect)), Some(font)) => {
+                            font.texture_mut().set_color_mod(r, g, b);
+                            texture_canvas
+                                .copy(font.texture(), srcrect, dstrect)
+                                .expect("failed to copy glyph");
+                        }
+                        (Some(GlyphSource::Tileset(srcrect)), _) => {
+                            tileset.texture_mut().set_color_mod(r, g, b);
+                            texture_canvas
+                                .copy(tileset.texture(), srcrect, dstrect)
+                                .expect("failed to copy tile");
+                        }
+                        _ => {}
+                    }
+                }
+            })
+            .map_err(|e| e.to_string())?;
+
+        self.canvas.set_draw_color(Color::RGBA(0, 0, 0, 255));
+
+        Ok(())
+    }
+}
+
+impl<'ttf, 'r> Backend for SdlBackend<'ttf, 'r> {
+    fn poll_input(&mut self) -> Result<Input, String> {
+        let mut input = Input::default();
+        let events: Vec<Event> = self.event_pump.poll_iter().collect();
+        for event in events {
+            match event {
+                Event::Quit { .. } => input.quit = true,
+                Event::MouseButtonDown {
+                    mouse_btn, x, y, ..
+                } => {
+                    if let Some(cell) = self.cell_at(x, y) {
+                        input.mouse.clicks.push((mouse_btn, cell));
+                    }
+                }
+                Event::MouseWheel {
+                    x, y, direction, ..
+                } => {
+                    let sign = match direction {
+                        MouseWheelDirection::Flipped => -1,
+                        _ => 1,
+                    };
+                    input.mouse.wheel.0 += x * sign;
+                    input.mouse.wheel.1 += y * sign;
+                }
+                Event::Window {
+                    win_event: WindowEvent::Resized { .. },
+                    ..
+                }
+                | Event::Window {
+                    win_event: WindowEvent::SizeChanged { .. },
+                    ..
+                } => self.dirty_window = true,
+                _ => {}
+            }
+        }
+
+        input.keycodes = self
+            .event_pump
+            .keyboard_state()
+            .pressed_scancodes()
+            .filter_map(Keycode::from_scancode)
+            .collect();
+
+        let mouse_state = self.event_pump.mouse_state();
+        let cell = self.cell_at(mouse_state.x(), mouse_state.y());
+        input.mouse = Mouse {
+            cell,
+            moved: cell != self.mouse_cell,
+            held: mouse_state.pressed_mouse_buttons().collect(),
+            ..input.mouse
+        };
+        self.mouse_cell = cell;
+
+        Ok(input)
+    }
+
+    fn present(&mut self, console: &Console) -> Result<(), String> {
+        if let (true, Some(frame_texture)) = (self.dirty_window, &self.frame_texture) {
+            let query = frame_texture.query();
+            let window = self.canvas.window().size();
+            let filter = scale_quality(self.config.scaling, window, (query.width, query.height));
+            if filter != self.filter {
+                let (tile_w, tile_h) = self.tileset.tile_size();
+                self.resize((query.width / tile_w, query.height / tile_h))?;
+            }
+        }
+
+        if self.redraw {
+            self.draw_tiles(console.tiles().iter())?;
+            self.redraw = false;
+        } else {
+            self.draw_tiles(console.dirty_tiles())?;
+        }
+
+        let frame_size = self.frame_texture.as_ref().map(|texture| {
+            let query = texture.query();
+            (query.width, query.height)
+        });
+        if let Some(frame_size) = frame_size {
+            if self.dirty_window {
+                self.dstrect =
+                    frame_dstrect(self.config.scaling, self.canvas.window().size(), frame_size);
+                self.dirty_window = false;
+            }
+            self.draw_letterbox(frame_size)?;
+            if let Some(frame_texture) = &self.frame_texture {
+                self.canvas.copy(frame_texture, None, self.dstrect)?;
+            }
+            self.canvas.present();
+        }
+
+        if Instant::now() - self.last_fps_print > Duration::new(5, 0) {
+            println!("fps: {}", self.fps.tick());
+            self.last_fps_print = Instant::now();
+        } else {
+            self.fps.tick();
+        }
+
+        Ok(())
+    }
+
+    fn resize(&mut self, (width, height): (u32, u32)) -> Result<(), String> {
+        let (tile_w, tile_h) = self.tileset.tile_size();
+        let frame = (tile_w * width, tile_h * height);
+        // Filtering is picked when the texture is made
+        self.filter = scale_quality(self.config.scaling, self.canvas.window().size(), frame);
+        sdl2::hint::set("SDL_RENDER_SCALE_QUALITY", self.filter);
+        self.frame_texture = Some(
+            self.texture_creator
+                .create_texture_target(PixelFormatEnum::RGBA8888, frame.0, frame.1)
+                .map_err(|e| e.to_string())?,
+        );
+        self.redraw = true;
+        self.dirty_window = true;
+        Ok(())
+    }
+
+    fn configure(&mut self, config: &Config) -> Result<(), String> {
+        self.config = config.clone();
+        // The next frame picks new filtering if the scaling needs it
+        self.dirty_window = true;
+        Ok(())
+    }
+
+    fn fitting_size(&self) -> Option<(u32, u32)> {
+        let (w, h) = self.canvas.window().size();
+        let (tile_w, tile_h) = self.tileset.tile_size();
+        Some(((w / tile_w).max(1), (h / tile_h).max(1)))
+    }
+
+    fn glyph(&self, name: &str) -> Option<Glyph> {
+        self.tileset.glyph(name)
+    }
+
+    fn screenshot(&mut self) -> Result<Option<RgbaImage>, String> {
+        let frame_texture = match &mut self.frame_texture {
+            Some(texture) => texture,
+            None => return Ok(None),
+        };
+        let query = frame_texture.query();
+        let mut pixels = Ok(Vec::new());
+        self.canvas
+            .with_texture_canvas(frame_texture, |texture_canvas| {
+                pixels = texture_canvas.read_pixels(None, PixelFormatEnum::RGBA32);
+            })
+            .map_err(|e| e.to_string())?;
+        Ok(Some(RgbaImage::from_pixels(
+            query.width,
+            query.height,
+            pixels?,
+        )))
+    }
+}
+
+impl<'ttf, 'r> Drop for SdlBackend<'ttf, 'r> {
+    fn drop(&mut self) {
+        self.canvas.window_mut().hide();
+    }
+}
@@ -0,0 +1,294 @@
+use std::collections::HashSet;
+use std::io::{self, Read, Write};
+use std::mem;
+use std::thread;
+use std::time::Duration;
+
+use sdl2::keyboard::Keycode;
+use sdl2::pixels::Color;
+
+use crate::backend::{Backend, Input};
+use crate::config::Config;
+use crate::console::{Console, Tile};
+use crate::glyph::Glyph;
+use crate::headless::RgbaImage;
+
+// Drawn to when the terminal doesn't report its size, e.g. with output redirected
+const DEFAULT_SIZE: (u32, u32) = (80, 24);
+
+/// Draws consoles to the controlling terminal with 24-bit colour escapes and reads
+/// keys from it. The terminal is put in raw mode until this is dropped.
+pub struct Terminal {
+    original: libc::termios,
+    out: io::BufWriter<io::Stdout>,
+    // Terminal size at the last full redraw
+    drawn_size: Option<(u32, u32)>,
+}
+
+impl Terminal {
+    pub fn new() -> io::Result<Self> {
+        let original = unsafe {
+            let mut termios = mem::zeroed();
+            if libc::tcgetattr(libc::STDIN_FILENO, &mut termios) != 0 {
+                return Err(io::Error::last_os_error());
+            }
+            let mut raw = termios;
+            libc::cfmakeraw(&mut raw);
+            // Reads return straight away with whatever input is waiting
+            raw.c_cc[libc::VMIN] = 0;
+            raw.c_cc[libc::VTIME] = 0;
+            if libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &raw) != 0 {
+                return Err(io::Error::last_os_error());
+            }
+            termios
+        };
+        let mut terminal = Self {
+            original,
+            out: io::BufWriter::new(io::stdout()),
+            drawn_size: None,
+        };
+        // Alternate screen, hidden cursor, cleared
+        write!(terminal.out, "\x1b[?1049h\x1b[?25l\x1b[2J")?;
+        terminal.out.flush()?;
+        Ok(terminal)
+    }
+
+    /// Returns the terminal size in cells, or `None` if it's unknown.
+    pub fn size(&self) -> Option<(u32, u32)> {
+        let size = unsafe {
+            let mut size: libc::winsize = mem::zeroed();
+            if libc::ioctl(libc::STDOUT_FILENO, libc::TIOCGWINSZ, &mut size) != 0 {
+                return None;
+            }
+            (size.ws_col as u32, size.ws_row as u32)
+        };
+        // Terminals that don't know their size report 0x0
+        match size {
+            (0, _) | (_, 0) => None,
+            size => Some(size),
+        }
+    }
+
+    /// Redraws `tiles`, e.g. `Console::dirty_tiles`, skipping any that don't fit.
+    pub fn draw<'a>(&mut self, tiles: impl Iterator<Item = &'a Tile>) -> io::Result<()> {
+        let (cols, rows) = self.size().unwrap_or(DEFAULT_SIZE);
+        let mut tiles: Vec<&Tile> = tiles.filter(|t| t.row < cols && t.col < rows).collect();
+        tiles.sort_by_key(|t| (t.col, t.row));
+
+        let mut cursor = None;
+        let mut colors = None;
+        for tile in tiles {
+            if cursor != Some((tile.row, tile.col)) {
+                write!(self.out, "\x1b[{};{}H", tile.col + 1, tile.row + 1)?;
+            }
+            if colors != Some((tile.foreground, tile.background)) {
+                let Color { r, g, b, .. } = tile.foreground;
+                write!(self.out, "\x1b[38;2;{};{};{}m", r, g, b)?;
+                let Color { r, g, b, .. } = tile.background;
+                write!(self.out, "\x1b[48;2;{};{};{}m", r, g, b)?;
+                colors = Some((tile.foreground, tile.background));
+            }
+            write!(self.out, "{}", tile.code_point.display_char())?;
+            cursor = Some((tile.row + 1, tile.col));
+        }
+        self.out.flush()
+    }
+
+    /// Clears the screen and draws every tile of `console`, e.g. after a resize.
+    pub fn redraw(&mut self, console: &Console) -> io::Result<()> {
+        write!(self.out, "\x1b[0m\x1b[2J")?;
+        self.draw(console.tiles().iter())
+    }
+
+    /// Returns the keys typed since the last call.
+    pub fn poll_keys(&mut self) -> io::Result<HashSet<Keycode>> {
+        let mut input = Vec::new();
+        let mut buf = [0u8; 64];
+        loop {
+            match io::stdin().read(&mut buf)? {
+                0 => break,
+                n => input.extend_from_slice(&buf[..n]),
+            }
+        }
+        Ok(parse_keys(&input))
+    }
+}
+
+impl Backend for Terminal {
+    fn poll_input(&mut self) -> Result<Input, String> {
+        Ok(Input {
+            keycodes: self.poll_keys().map_err(|e| e.to_string())?,
+            ..Input::default()
+        })
+    }
+
+    fn present(&mut self, console: &Console) -> Result<(), String> {
+        let size = self.size().unwrap_or(DEFAULT_SIZE);
+        if self.drawn_size != Some(size) {
+            self.drawn_size = Some(size);
+            self.redraw(console)
+        } else {
+            self.draw(console.dirty_tiles())
+        }
+        .map_err(|e| e.to_string())?;
+
+        // Nothing waits for vsync here
+        thread::sleep(Duration::from_millis(16));
+        Ok(())
+    }
+
+    fn resize(&mut self, _size: (u32, u32)) -> Result<(), String> {
+        self.drawn_size = None;
+        Ok(())
+    }
+
+    fn configure(&mut self, _config: &Config) -> Result<(), String> {
+        Ok(())
+    }
+
+    fn fitting_size(&self) -> Option<(u32, u32)> {
+        self.size()
+    }
+
+    // Only characters are drawn, there's no sheet to name glyphs in
+    fn glyph(&self, _name: &str) -> Option<Glyph> {
+        None
+    }
+
+    fn screenshot(&mut self) -> Result<Option<RgbaImage>, String> {
+        Ok(None)
+    }
+}
+
+impl Drop for Terminal {
+    fn drop(&mut self) {
+        let _ = write!(self.out, "\x1b[0m\x1b[2J\x1b[?25h\x1b[?1049l");
+        let _ = self.out.flush();
+        unsafe {
+            libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &self.original);
+        }
+    }
+}
+
+fn parse_keys(input: &[u8]) -> HashSet<Keycode> {
+    let mut keys = HashSet::new();
+    let mut i = 0;
+    while i < input.len() {
+        let (key, len) = match input[i..] {
+            [0x1b, b'[', ..] => {
+                // Control sequences run up to the first byte in the final byte range
+                let start = i + 2;
+                match input[start..]
+                    .iter()
+                    .position(|b| (0x40..=0x7e).contains(b))
+                {
+                    Some(end) => (
+                        csi_key(&input[start..start + end], input[start + end]),
+                        end + 3,
+                    ),
+                    None => (None, input.len() - i),
+                }
+            }
+            [0x1b, b'O', c, ..] => (ss3_key(c), 3),
+            // Escape on its own, or twice in a row; anything else is Alt with a key
+            [0x1b] | [0x1b, 0x1b, ..] => (Some(Keycode::Escape), 1),
+            [0x1b, ..] => (None, 1),
+            [b'\r', ..] | [b'\n', ..] => (Some(Keycode::Return), 1),
+            [b'\t', ..] => (Some(Keycode::Tab), 1),
+            [0x7f, ..] => (Some(Keycode::Backspace), 1),
+            // Printable ASCII keycodes are the lower case characters themselves, except
+            // for `{|}~` which SDL has no keycodes for
+            [c, ..] => match c.to_ascii_lowercase() {
+                c @ (b' '..=b'@' | b'['..=b'`' | b'a'..=b'z') => (Keycode::from_i32(c as i32), 1),
+                _ => (None, 1),
+            },
+            [] => break,
+        };
+        keys.extend(key);
+        i += len;
+    }
+    keys
+}
+
+// The key for `ESC [ params final`, ignoring modifier parameters
+fn csi_key(params: &[u8], final_byte: u8) -> Option<Keycode> {
+    let first = params
+        .split(|&b| b == b';')
+        .next()
+        .and_then(|p| std::str::from_utf8(p).ok())
+        .and_then(|p| p.parse::<u32>().ok());
+    match (final_byte, first) {
+        (b'~', Some(n)) => match n {
+            1 | 7 => Some(Keycode::Home),
+            2 => Some(Keycode::Insert),
+            3 => Some(Keycode::Delete),
+            4 | 8 => Some(Keycode::End),
+            5 => Some(Keycode::PageUp),
+            6 => Some(Keycode::PageDown),
+            11 => Some(Keycode::F1),
+            12 => Some(Keycode::F2),
+            13 => Some(Keycode::F3),
+            14 => Some(Keycode::F4),
+            15 => Some(Keycode::F5),
+            17 => Some(Keycode::F6),
+            18 => Some(Keycode::F7),
+            19 => Some(Keycode::F8),
+            20 => Some(Keycode::F9),
+            21 => Some(Keycode::F10),
+            23 => Some(Keycode::F11),
+            24 => Some(Keycode::F12),
+            _ => None,
+        },
+        (c, _) => ss3_key(c),
+    }
+}
+
+// The key for `ESC O c`, also used for modified keys like `ESC [ 1 ; 5 A`
+fn ss3_key(c: u8) -> Option<Keycode> {
+    match c {
+        b'A' => Some(Keycode::Up),
+        b'B' => Some(Keycode::Down),
+        b'C' => Some(Keycode::Right),
+        b'D' => Some(Keycode::Left),
+        b'H' => Some(Keycode::Home),
+        b'F' => Some(Keycode::End),
+        b'P' => Some(Keycode::F1),
+        b'Q' => Some(Keycode::F2),
+        b'R' => Some(Keycode::F3),
+        b'S' => Some(Keycode::F4),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn keys(input: &[u8]) -> Vec<Keycode> {
+        let mut keys: Vec<Keycode> = parse_keys(input).into_iter().collect();
+        keys.sort_by_key(|k| *k as i32);
+        keys
+    }
+
+    #[test]
+    fn parses_control_sequences_whole() {
+        assert_eq!(keys(b"\x1b[21~"), vec![Keycode::F10]);
+        assert_eq!(keys(b"\x1b[23~"), vec![Keycode::F11]);
+        assert_eq!(keys(b"\x1b[3~"), vec![Keycode::Delete]);
+        assert_eq!(keys(b"\x1b[1;5A"), vec![Keycode::Up]);
+        assert_eq!(keys(b"\x1bOP"), vec![Keycode::F1]);
+        assert_eq!(keys(b"\x1b[99"), vec![]);
+    }
+
+    #[test]
+    fn only_lone_escapes_are_escape() {
+        assert_eq!(keys(b"\x1b"), vec![Keycode::Escape]);
+        assert_eq!(keys(b"\x1bx"), vec![Keycode::X]);
+    }
+
+    #[test]
+    fn drops_bytes_without_keycodes() {
+        assert_eq!(keys(b"~{|}\x01\x1f"), vec![]);
+        assert_eq!(keys(b"A1"), vec![Keycode::Num1, Keycode::A]);
+    }
+}
@@ -0,0 +1,196 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+
+use sdl2::image::LoadSurface;
+use sdl2::pixels::Color;
+use sdl2::rect::Rect;
+use sdl2::render::{Texture, TextureCreator};
+use sdl2::surface::Surface;
+use sdl2::video::WindowContext;
+
+use crate::glyph::{Glyph, GlyphMap};
+
+#[derive(Debug, Copy, Clone)]
+pub struct Coords {
+    pub row: i32,
+    pub col: i32,
+}
+
+impl Coords {
+    #[allow(dead_code)]
+    pub fn new(row: i32, col: i32) -> Self {
+        Self { row, col }
+    }
+}
+
+/// Describes how glyphs are laid out in a tile sheet image.
+#[derive(Debug, Clone, Deserialize)]
+pub struct TilesetDesc {
+    pub path: PathBuf,
+    pub tile_size: (u32, u32),
+    /// Number of glyphs per row, defaults to as many as fit in the image.
+    #[serde(default)]
+    pub columns: Option<u32>,
+    /// Pixels between the image edge and the first glyph.
+    #[serde(default)]
+    pub margin: (u32, u32),
+    /// Pixels between neighbouring glyphs.
+    #[serde(default)]
+    pub padding: (u32, u32),
+    /// Pixels of this colour are made transparent.
+    #[serde(default)]
+    pub key: Option<(u8, u8, u8)>,
+    /// RON file naming cells of the sheet, see `GlyphMap`.
+    #[serde(default)]
+    pub glyphs: Option<PathBuf>,
+    /// TrueType font rasterised at the tile size for `Glyph::from_char` glyphs.
+    #[serde(default)]
+    pub font: Option<PathBuf>,
+}
+
+impl TilesetDesc {
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let file = File::open(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        ron::de::from_reader(file).map_err(|e| format!("{}: {}", path.display(), e))
+    }
+
+    pub fn load_surface(&self) -> Result<Surface<'static>, String> {
+        let mut surface = Surface::from_file(&self.path)?;
+        if let Some((r, g, b)) = self.key {
+            surface.set_color_key(true, Color::RGB(r, g, b))?;
+        }
+        Ok(surface)
+    }
+}
+
+/// Where each glyph sits in a sheet image.
+#[derive(Debug, Clone)]
+pub struct SheetLayout {
+    tile_size: (u32, u32),
+    columns: u32,
+    rows: u32,
+    margin: (u32, u32),
+    padding: (u32, u32),
+}
+
+impl SheetLayout {
+    pub fn new(desc: &TilesetDesc, (width, height): (u32, u32)) -> Result<Self, String> {
+        let (w, h) = desc.tile_size;
+        if w == 0 || h == 0 {
+            return Err(format!(
+                "{}: tile size must be non-zero",
+                desc.path.display()
+            ));
+        }
+        let fit = |size: u32, margin: u32, tile: u32, padding: u32| {
+            (size.saturating_sub(margin) + padding) / (tile + padding)
+        };
+        let max_columns = fit(width, desc.margin.0, w, desc.padding.0);
+        let rows = fit(height, desc.margin.1, h, desc.padding.1);
+        let columns = desc.columns.unwrap_or(max_columns);
+        if columns == 0 || columns > max_columns || rows == 0 {
+            return Err(format!(
+                "{}: {}x{} image has no room for a {}-column grid of {}x{} tiles",
+                desc.path.display(),
+                width,
+                height,
+                columns,
+                w,
+                h
+            ));
+        }
+        Ok(Self {
+            tile_size: desc.tile_size,
+            columns,
+            rows,
+            margin: desc.margin,
+            padding: desc.padding,
+        })
+    }
+
+    pub fn tile_size(&self) -> (u32, u32) {
+        self.tile_size
+    }
+
+    pub fn glyph_count(&self) -> u32 {
+        self.columns * self.rows
+    }
+
+    pub fn coords(&self, glyph: Glyph) -> Coords {
+        Coords {
+            row: (glyph.id() % self.columns) as i32,
+            col: (glyph.id() / self.columns) as i32,
+        }
+    }
+
+    /// Resolves the glyph map named by `desc`, if any, against this layout.
+    pub fn glyph_names(&self, desc: &TilesetDesc) -> Result<HashMap<String, Glyph>, String> {
+        match &desc.glyphs {
+            Some(path) => GlyphMap::load(path)?.resolve(self.columns, self.rows),
+            None => Ok(HashMap::new()),
+        }
+    }
+
+    /// Returns the area of the sheet holding `glyph`, or `None` if the sheet is too small.
+    pub fn srcrect(&self, glyph: Glyph) -> Option<Rect> {
+        if glyph.id() >= self.glyph_count() {
+            return None;
+        }
+        let coords = self.coords(glyph);
+        Some(Rect::new(
+            (self.margin.0 + (self.tile_size.0 + self.padding.0) * coords.row as u32) as i32,
+            (self.margin.1 + (self.tile_size.1 + self.padding.1) * coords.col as u32) as i32,
+            self.tile_size.0,
+            self.tile_size.1,
+        ))
+    }
+}
+
+pub struct Tileset<'r> {
+    texture: Texture<'r>,
+    layout: SheetLayout,
+    names: HashMap<String, Glyph>,
+}
+
+impl<'r> Tileset<'r> {
+    pub fn new(
+        texture_creator: &'r TextureCreator<WindowContext>,
+        desc: &TilesetDesc,
+        surface: Surface,
+    ) -> Result<Self, String> {
+        let layout = SheetLayout::new(desc, surface.size())?;
+        let names = layout.glyph_names(desc)?;
+        let texture = texture_creator
+            .create_texture_from_surface(surface)
+            .map_err(|e| e.to_string())?;
+        Ok(Self {
+            texture,
+            layout,
+            names,
+        })
+    }
+
+    pub fn texture(&self) -> &Texture<'r> {
+        &self.texture
+    }
+
+    pub fn texture_mut(&mut self) -> &mut Texture<'r> {
+        &mut self.texture
+    }
+
+    pub fn tile_size(&self) -> (u32, u32) {
+        self.layout.tile_size()
+    }
+
+    /// Looks up a glyph by the name given to it in the glyph map.
+    pub fn glyph(&self, name: &str) -> Option<Glyph> {
+        self.names.get(name).copied()
+    }
+
+    pub fn srcrect(&self, glyph: Glyph) -> Option<Rect> {
+        self.layout.srcrect(glyph)
+    }
+}
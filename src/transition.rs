@@ -0,0 +1,257 @@
+use rand::prelude::*;
+
+use specs::prelude::*;
+
+use sdl2::pixels::Color;
+
+use crate::animation::FrameTime;
+use crate::color;
+use crate::console::Console;
+use crate::cp437::Cp437;
+use crate::glyph::Glyph;
+use crate::layers::Layers;
+
+/// The way a wipe travels across the screen.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Direction {
+    Left,
+    Right,
+    Up,
+    Down,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum TransitionStyle {
+    /// Fades out to black, then in to the new screen.
+    Fade,
+    /// Draws the new screen over the old from one edge.
+    Wipe(Direction),
+    /// Swaps tiles over one at a time in a random order.
+    Dissolve,
+    /// Tiles show random glyphs while their colours blend, settling on the new screen
+    /// in a random order.
+    Scramble,
+}
+
+/// Blends one console snapshot into another over time.
+pub struct Transition {
+    from: Console,
+    to: Console,
+    style: TransitionStyle,
+    /// In seconds.
+    duration: f32,
+    elapsed: f32,
+    // Progress at which each tile switches to `to`, for dissolving and scrambling
+    thresholds: Vec<f32>,
+}
+
+impl Transition {
+    /// A transition from `from` to `to`, which should be what the console shows once
+    /// the transition is over.
+    pub fn new(from: Console, to: Console, style: TransitionStyle, duration: f32) -> Self {
+        let mut rng = thread_rng();
+        let thresholds = (0..to.tiles().len()).map(|_| rng.gen()).collect();
+        Self {
+            from,
+            to,
+            style,
+            duration,
+            elapsed: 0.0,
+            thresholds,
+        }
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.elapsed >= self.duration
+    }
+
+    /// Draws the transition `progress` of the way through, from 0 to 1, onto `target`.
+    fn draw(&self, progress: f32, target: &mut Console) {
+        let black = Color::RGBA(0, 0, 0, 255);
+        let (width, height) = (self.to.width(), self.to.height());
+        let mut rng = thread_rng();
+        for (i, to) in self.to.tiles().iter().enumerate() {
+            let (x, y) = (to.row, to.col);
+            let from = self
+                .from
+                .tile(x, y)
+                .map_or((Cp437::Null.into(), black, black), |tile| {
+                    (tile.code_point, tile.foreground, tile.background)
+                });
+            let to = (to.code_point, to.foreground, to.background);
+            let (glyph, fg, bg): (Glyph, Color, Color) = match self.style {
+                TransitionStyle::Fade if progress < 0.5 => {
+                    let t = progress * 2.0;
+                    (
+                        from.0,
+                        color::lerp(from.1, black, t),
+                        color::lerp(from.2, black, t),
+                    )
+                }
+                TransitionStyle::Fade => {
+                    let t = progress * 2.0 - 1.0;
+                    (
+                        to.0,
+                        color::lerp(black, to.1, t),
+                        color::lerp(black, to.2, t),
+                    )
+                }
+                TransitionStyle::Wipe(direction) => {
+                    // How far along the wipe the tile's edge nearest the start is
+                    let along = match direction {
+                        Direction::Right => x as f32 / width as f32,
+                        Direction::Left => (width - 1 - x) as f32 / width as f32,
+                        Direction::Down => y as f32 / height as f32,
+                        Direction::Up => (height - 1 - y) as f32 / height as f32,
+                    };
+                    if along < progress {
+                        to
+                    } else {
+                        from
+                    }
+                }
+                TransitionStyle::Dissolve if self.thresholds[i] < progress => to,
+                TransitionStyle::Dissolve => from,
+                TransitionStyle::Scramble if self.thresholds[i] < progress => to,
+                TransitionStyle::Scramble => (
+                    Cp437::from(rng.gen::<u8>()).into(),
+                    color::lerp(from.1, to.1, progress),
+                    color::lerp(from.2, to.2, progress),
+                ),
+            };
+            target.put(x as i32, y as i32, glyph, fg, bg);
+        }
+    }
+}
+
+/// The transition being played, if any. Input is held back from systems while one is.
+#[derive(Default)]
+pub struct ActiveTransition(pub Option<Transition>);
+
+/// Draws the active transition over the console, after the layers are composited and
+/// animated.
+pub struct TransitionSys;
+
+impl<'a> System<'a> for TransitionSys {
+    type SystemData = (
+        Read<'a, FrameTime>,
+        Write<'a, ActiveTransition>,
+        Write<'a, Layers>,
+        Write<'a, Console>,
+    );
+
+    fn run(&mut self, (time, mut active, mut layers, mut console): Self::SystemData) {
+        let transition = match active.0.as_mut() {
+            Some(transition) => transition,
+            None => return,
+        };
+        transition.elapsed += time.delta;
+        let progress = if transition.duration > 0.0 {
+            (transition.elapsed / transition.duration).min(1.0)
+        } else {
+            1.0
+        };
+        transition.draw(progress, &mut console);
+        if transition.is_done() {
+            active.0 = None;
+            // The console holds the last transition frame rather than the layers
+            layers.invalidate();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn filled(glyph: Cp437, fg: Color, bg: Color) -> Console {
+        let mut console = Console::new(4, 2);
+        console.clear(glyph.into(), fg, bg);
+        console
+    }
+
+    fn transition(style: TransitionStyle) -> Transition {
+        let from = filled(
+            Cp437::LatinCapitalLetterA,
+            Color::RGBA(200, 0, 0, 255),
+            Color::RGBA(0, 0, 200, 255),
+        );
+        let to = filled(
+            Cp437::LatinCapitalLetterB,
+            Color::RGBA(0, 200, 0, 255),
+            Color::RGBA(100, 100, 100, 255),
+        );
+        Transition::new(from, to, style, 1.0)
+    }
+
+    fn tiles(console: &Console) -> Vec<(Glyph, Color, Color)> {
+        console
+            .tiles()
+            .iter()
+            .map(|tile| (tile.code_point, tile.foreground, tile.background))
+            .collect()
+    }
+
+    fn drawn(transition: &Transition, progress: f32) -> Vec<(Glyph, Color, Color)> {
+        let mut target = Console::new(4, 2);
+        transition.draw(progress, &mut target);
+        tiles(&target)
+    }
+
+    #[test]
+    fn fades_through_black() {
+        let fade = transition(TransitionStyle::Fade);
+        assert_eq!(drawn(&fade, 0.0), tiles(&fade.from));
+        let black = Color::RGBA(0, 0, 0, 255);
+        let glyph = Cp437::LatinCapitalLetterB.into();
+        assert!(drawn(&fade, 0.5)
+            .iter()
+            .all(|&t| t == (glyph, black, black)));
+        assert_eq!(drawn(&fade, 1.0), tiles(&fade.to));
+    }
+
+    #[test]
+    fn wipes_from_the_starting_edge() {
+        let wipe = transition(TransitionStyle::Wipe(Direction::Right));
+        assert_eq!(drawn(&wipe, 0.0), tiles(&wipe.from));
+        // The left half has been wiped over
+        let (from, to) = (tiles(&wipe.from), tiles(&wipe.to));
+        for (i, tile) in drawn(&wipe, 0.5).into_iter().enumerate() {
+            let expected = if i % 4 < 2 { to[i] } else { from[i] };
+            assert_eq!(tile, expected);
+        }
+        assert_eq!(drawn(&wipe, 1.0), tiles(&wipe.to));
+
+        let wipe = transition(TransitionStyle::Wipe(Direction::Up));
+        for (i, tile) in drawn(&wipe, 0.5).into_iter().enumerate() {
+            let expected = if i / 4 == 1 { to[i] } else { from[i] };
+            assert_eq!(tile, expected);
+        }
+    }
+
+    #[test]
+    fn dissolves_tile_by_tile() {
+        let dissolve = transition(TransitionStyle::Dissolve);
+        let (from, to) = (tiles(&dissolve.from), tiles(&dissolve.to));
+        assert_eq!(drawn(&dissolve, 0.0), from);
+        for (i, tile) in drawn(&dissolve, 0.5).into_iter().enumerate() {
+            assert!(tile == from[i] || tile == to[i]);
+        }
+        assert_eq!(drawn(&dissolve, 1.0), to);
+    }
+
+    #[test]
+    fn scrambles_then_settles() {
+        let scramble = transition(TransitionStyle::Scramble);
+        let (from, to) = (tiles(&scramble.from), tiles(&scramble.to));
+        // Glyphs are random until a tile settles, but colours start where they were
+        for (i, tile) in drawn(&scramble, 0.0).into_iter().enumerate() {
+            assert_eq!((tile.1, tile.2), (from[i].1, from[i].2));
+        }
+        let halfway = (Color::RGBA(100, 100, 0, 255), Color::RGBA(50, 50, 150, 255));
+        for (i, tile) in drawn(&scramble, 0.5).into_iter().enumerate() {
+            assert!(tile == to[i] || (tile.1, tile.2) == halfway);
+        }
+        assert_eq!(drawn(&scramble, 1.0), to);
+    }
+}
@@ -0,0 +1,214 @@
+// REXPaint `.xp` images are a gzipped version number and layer count, then each
+// layer's size and its cells in column-major order as a little-endian glyph index
+// followed by RGB foreground and background bytes.
+
+use std::convert::TryInto;
+use std::fs::File;
+use std::io::{Read, Write};
+use std::path::Path;
+
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+
+use sdl2::pixels::Color;
+
+use crate::console::Console;
+use crate::cp437::Cp437;
+use crate::glyph::Glyph;
+
+const VERSION: i32 = -1;
+const CELL_SIZE: usize = 10;
+// REXPaint marks transparent cells with a magenta background
+const TRANSPARENT: (u8, u8, u8) = (255, 0, 255);
+
+/// Loads every layer of an image, bottom first.
+///
+/// Transparent cells get zero alpha foreground and background colours, so they
+/// disappear when composited with `Layers`.
+#[allow(dead_code)]
+pub fn load(path: &Path) -> Result<Vec<Console>, String> {
+    let mut data = Vec::new();
+    File::open(path)
+        .and_then(|file| GzDecoder::new(file).read_to_end(&mut data))
+        .map_err(|e| format!("{}: {}", path.display(), e))?;
+    let mut reader = Reader {
+        data: &data,
+        pos: 0,
+    };
+    parse(&mut reader).map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+/// Saves `layers` as an image, bottom first. They must all be the same size.
+///
+/// Tiles with a transparent background are written as transparent cells. Glyphs
+/// without a CP437 equivalent become question marks.
+#[allow(dead_code)]
+pub fn save(path: &Path, layers: &[&Console]) -> Result<(), String> {
+    let (width, height) = match layers.first() {
+        Some(console) => (console.width(), console.height()),
+        None => return Err(format!("{}: no layers to save", path.display())),
+    };
+    if layers
+        .iter()
+        .any(|console| (console.width(), console.height()) != (width, height))
+    {
+        return Err(format!("{}: layers differ in size", path.display()));
+    }
+
+    let cells = (width * height) as usize;
+    let mut data = Vec::with_capacity(8 + layers.len() * (8 + cells * CELL_SIZE));
+    data.extend_from_slice(&VERSION.to_le_bytes());
+    data.extend_from_slice(&(layers.len() as i32).to_le_bytes());
+    for console in layers {
+        data.extend_from_slice(&(width as i32).to_le_bytes());
+        data.extend_from_slice(&(height as i32).to_le_bytes());
+        let tiles = console.tiles();
+        for x in 0..width {
+            for y in 0..height {
+                let tile = &tiles[(y * width + x) as usize];
+                let index = tile
+                    .code_point
+                    .sheet_glyph()
+                    .unwrap_or_else(|| Cp437::QuestionMark.into())
+                    .id();
+                let fg = tile.foreground;
+                let bg = match tile.background {
+                    Color { a: 0, .. } => TRANSPARENT,
+                    Color { r, g, b, .. } => (r, g, b),
+                };
+                data.extend_from_slice(&index.to_le_bytes());
+                data.extend_from_slice(&[fg.r, fg.g, fg.b, bg.0, bg.1, bg.2]);
+            }
+        }
+    }
+
+    File::create(path)
+        .and_then(|file| {
+            let mut encoder = GzEncoder::new(file, Compression::default());
+            encoder.write_all(&data)?;
+            encoder.finish().map(|_| ())
+        })
+        .map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn bytes(&mut self, len: usize) -> Result<&'a [u8], String> {
+        let bytes = self
+            .pos
+            .checked_add(len)
+            .and_then(|end| self.data.get(self.pos..end))
+            .ok_or_else(|| format!("truncated at byte {}", self.pos))?;
+        self.pos += len;
+        Ok(bytes)
+    }
+
+    fn i32(&mut self) -> Result<i32, String> {
+        Ok(i32::from_le_bytes(self.bytes(4)?.try_into().unwrap()))
+    }
+}
+
+fn parse(reader: &mut Reader) -> Result<Vec<Console>, String> {
+    let version = reader.i32()?;
+    if version >= 0 {
+        return Err(format!("unsupported version {}", version));
+    }
+    let layer_count = reader.i32()?;
+    if layer_count <= 0 {
+        return Err(format!("bad layer count {}", layer_count));
+    }
+
+    // The count isn't trusted for preallocating, each layer has to be there in full
+    let mut layers = Vec::new();
+    for _ in 0..layer_count {
+        let (width, height) = (reader.i32()?, reader.i32()?);
+        if width <= 0 || height <= 0 {
+            return Err(format!("bad layer size {}x{}", width, height));
+        }
+        let (width, height) = (width as u32, height as u32);
+        let len = (width as usize)
+            .checked_mul(height as usize)
+            .and_then(|cells| cells.checked_mul(CELL_SIZE))
+            .ok_or_else(|| format!("bad layer size {}x{}", width, height))?;
+        let cells = reader.bytes(len)?;
+
+        let mut console = Console::new(width, height);
+        for (i, cell) in cells.chunks(CELL_SIZE).enumerate() {
+            let (x, y) = (i as u32 / height, i as u32 % height);
+            let index = u32::from_le_bytes(cell[0..4].try_into().unwrap());
+            let glyph = match index {
+                0..=255 => Cp437::from(index as u8).into(),
+                _ => Glyph(index),
+            };
+            let (fg, bg) = match (cell[7], cell[8], cell[9]) {
+                TRANSPARENT => (
+                    Color::RGBA(cell[4], cell[5], cell[6], 0),
+                    Color::RGBA(TRANSPARENT.0, TRANSPARENT.1, TRANSPARENT.2, 0),
+                ),
+                (r, g, b) => (Color::RGB(cell[4], cell[5], cell[6]), Color::RGB(r, g, b)),
+            };
+            console.put(x as i32, y as i32, glyph, fg, bg);
+        }
+        layers.push(console);
+    }
+    Ok(layers)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips_layers() {
+        let mut bottom = Console::new(3, 2);
+        bottom.clear(
+            Cp437::FullStop.into(),
+            Color::RGB(1, 2, 3),
+            Color::RGB(4, 5, 6),
+        );
+        bottom.put(
+            2,
+            1,
+            Cp437::CommercialAt.into(),
+            Color::RGB(7, 8, 9),
+            Color::RGB(10, 11, 12),
+        );
+        let mut top = Console::new(3, 2);
+        top.clear(
+            Cp437::Space.into(),
+            Color::RGBA(0, 0, 0, 0),
+            Color::RGBA(0, 0, 0, 0),
+        );
+
+        let path = std::env::temp_dir().join(format!("xp-test-{}.xp", std::process::id()));
+        save(&path, &[&bottom, &top]).unwrap();
+        let layers = load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(layers.len(), 2);
+        let tile = &layers[0].tiles()[5];
+        assert_eq!(tile.code_point, Cp437::CommercialAt.into());
+        assert_eq!(tile.foreground, Color::RGB(7, 8, 9));
+        assert_eq!(tile.background, Color::RGB(10, 11, 12));
+        assert_eq!(layers[1].tiles()[0].background.a, 0);
+    }
+
+    #[test]
+    fn rejects_counts_the_data_doesnt_hold() {
+        let mut data = Vec::new();
+        data.extend_from_slice(&VERSION.to_le_bytes());
+        data.extend_from_slice(&i32::MAX.to_le_bytes());
+        data.extend_from_slice(&i32::MAX.to_le_bytes());
+        data.extend_from_slice(&i32::MAX.to_le_bytes());
+        let mut reader = Reader {
+            data: &data,
+            pos: 0,
+        };
+        assert!(parse(&mut reader).is_err());
+    }
+}